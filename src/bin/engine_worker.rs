@@ -0,0 +1,7 @@
+// The entry point of the web worker the browser runs the engine in, which trunk builds alongside the page.
+
+use rust_chess::wasm::engine_worker::run_engine_worker;
+
+fn main() {
+    run_engine_worker();
+}
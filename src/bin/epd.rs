@@ -0,0 +1,198 @@
+// Runs the engine on a test suite of EPD positions, like WAC or STS, to see how many it solves.
+//
+//     cargo run --release --bin epd -- <epd file> [--time <ms> | --depth <plies>] [--csv <file>]
+//
+// A position counts as solved when the engine plays one of its `bm` moves and none of its `am` moves.
+// Every run ends with a `summary` line of key=value pairs, and --csv writes out the result of every
+// position, so that two builds can be compared.
+
+use std::{env, fmt::Write as _, fs, process, sync::atomic::AtomicBool};
+
+use chess::ChessMove;
+use rust_chess::{
+    constants::DEFAULT_HASH_MB,
+    epd::Epd,
+    game_history::GameHistory,
+    san::move_to_san,
+    search::{iterative_deepening, SearchOptions},
+    search_limits::{SearchLimits, SystemClock},
+    transposition_table::TranspositionTable,
+};
+
+const DEFAULT_MOVE_TIME_MS: u64 = 1000;
+
+struct Options {
+    path: String,
+    limits: SearchLimits,
+    csv_path: Option<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut arguments = env::args().skip(1);
+    let mut path = None;
+    let mut limits = SearchLimits::move_time(DEFAULT_MOVE_TIME_MS);
+    let mut csv_path = None;
+    while let Some(argument) = arguments.next() {
+        let mut value = || {
+            arguments
+                .next()
+                .ok_or_else(|| format!("{} needs a value", argument))
+        };
+        match argument.as_str() {
+            "--time" => {
+                let time = value()?;
+                limits = SearchLimits::move_time(
+                    time.parse().map_err(|_| format!("invalid time {}", time))?,
+                );
+            }
+            "--depth" => {
+                let depth = value()?;
+                limits = SearchLimits::depth(
+                    depth
+                        .parse()
+                        .map_err(|_| format!("invalid depth {}", depth))?,
+                );
+            }
+            "--csv" => csv_path = Some(value()?),
+            _ if path.is_none() => path = Some(argument),
+            _ => return Err(format!("unexpected argument {}", argument)),
+        }
+    }
+    let path = path.ok_or("no EPD file given")?;
+    Ok(Options {
+        path,
+        limits,
+        csv_path,
+    })
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("usage: epd <epd file> [--time <ms> | --depth <plies>] [--csv <file>]");
+            process::exit(1);
+        }
+    };
+    let text = match fs::read_to_string(&options.path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("could not read {}: {}", options.path, error);
+            process::exit(1);
+        }
+    };
+
+    let mut transposition_table = TranspositionTable::new(DEFAULT_HASH_MB);
+    let mut csv = String::from("id,solved,move,depth,nodes,time_ms\n");
+    let (mut solved, mut total, mut total_nodes, mut total_time_ms) = (0, 0, 0, 0);
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (epd, best_moves, avoid_moves) = match read_test(line) {
+            Ok(test) => test,
+            Err(message) => {
+                eprintln!("line {}: {}", line_number, message);
+                continue;
+            }
+        };
+        let id = epd
+            .id()
+            .map_or_else(|| format!("line {}", line_number), String::from);
+
+        // every position is solved from scratch, so the results don't depend on the order of the file
+        transposition_table.clear();
+        let stop = AtomicBool::new(false);
+        let result = iterative_deepening(
+            &epd.board,
+            &GameHistory::default(),
+            &options.limits,
+            &SearchOptions::default(),
+            &mut transposition_table,
+            &SystemClock,
+            &stop,
+            |_| {},
+        );
+        let Some(chess_move) = result.best_move else {
+            eprintln!("{}: there are no legal moves", id);
+            continue;
+        };
+        let is_solved = (best_moves.is_empty() || best_moves.contains(&chess_move))
+            && !avoid_moves.contains(&chess_move);
+
+        let played = move_to_san(&epd.board, chess_move);
+        let mut expected = String::new();
+        for (opcode, moves) in [("bm", &best_moves), ("am", &avoid_moves)] {
+            if !moves.is_empty() {
+                let _ = write!(expected, " {} {}", opcode, san_list(&epd, moves));
+            }
+        }
+        println!(
+            "{:<16} {:<6} {:<8} expected{:<20} depth {:>2} nodes {:>10} time {:>6} ms",
+            id,
+            if is_solved { "solved" } else { "failed" },
+            played,
+            expected,
+            result.depth,
+            result.nodes,
+            result.elapsed_ms
+        );
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            id.replace(',', " "),
+            is_solved,
+            played,
+            result.depth,
+            result.nodes,
+            result.elapsed_ms
+        );
+
+        total += 1;
+        if is_solved {
+            solved += 1;
+        }
+        total_nodes += result.nodes;
+        total_time_ms += result.elapsed_ms;
+    }
+
+    println!();
+    println!(
+        "solved {}/{} ({:.1}%), searched {} nodes in {} ms",
+        solved,
+        total,
+        solved as f64 * 100.0 / total.max(1) as f64,
+        total_nodes,
+        total_time_ms
+    );
+    println!(
+        "summary file={} solved={} total={} nodes={} time_ms={}",
+        options.path, solved, total, total_nodes, total_time_ms
+    );
+    if let Some(csv_path) = &options.csv_path {
+        if let Err(error) = fs::write(csv_path, csv) {
+            eprintln!("could not write {}: {}", csv_path, error);
+        }
+    }
+}
+
+// the position along with its best and avoid moves. a test without either can't be judged
+fn read_test(line: &str) -> Result<(Epd, Vec<ChessMove>, Vec<ChessMove>), String> {
+    let epd = Epd::parse(line).map_err(|error| error.to_string())?;
+    let best_moves = epd.moves("bm").map_err(|error| error.to_string())?;
+    let avoid_moves = epd.moves("am").map_err(|error| error.to_string())?;
+    if best_moves.is_empty() && avoid_moves.is_empty() {
+        return Err("no bm or am to check the engine's move against".to_string());
+    }
+    Ok((epd, best_moves, avoid_moves))
+}
+
+fn san_list(epd: &Epd, moves: &[ChessMove]) -> String {
+    moves
+        .iter()
+        .map(|chess_move| move_to_san(&epd.board, *chess_move))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
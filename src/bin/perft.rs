@@ -0,0 +1,44 @@
+// Counts the leaf nodes of the move tree from a position, with a breakdown by first move.
+//
+//     cargo run --release --bin perft -- <depth> [fen]
+//
+// The output is in the same format as Stockfish's `go perft`, so the two can be diffed to find a bug.
+
+use std::{env, process, str::FromStr, time::Instant};
+
+use chess::Board;
+use rust_chess::perft::divide;
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let Some(depth) = arguments.first().and_then(|depth| depth.parse::<u8>().ok()) else {
+        eprintln!("usage: perft <depth> [fen]");
+        process::exit(1);
+    };
+    let board = if arguments.len() > 1 {
+        match Board::from_str(&arguments[1..].join(" ")) {
+            Ok(board) => board,
+            Err(error) => {
+                eprintln!("invalid fen: {}", error);
+                process::exit(1);
+            }
+        }
+    } else {
+        Board::default()
+    };
+
+    let start = Instant::now();
+    let mut nodes = 0;
+    for (chess_move, move_nodes) in divide(&board, depth) {
+        println!("{}: {}", chess_move, move_nodes);
+        nodes += move_nodes;
+    }
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    println!();
+    println!("Nodes searched: {}", nodes);
+    println!(
+        "Time: {} ms ({} nodes per second)",
+        elapsed_ms,
+        nodes * 1000 / elapsed_ms.max(1)
+    );
+}
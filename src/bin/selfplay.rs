@@ -0,0 +1,585 @@
+// Plays two configurations of the engine against each other to find out whether a change makes it stronger.
+//
+//     cargo run --release --bin selfplay -- --engine name=new,weights=tuned.rs --engine name=base \
+//         [--time <ms> | --depth <plies>] [--games <n>] [--openings <epd file>] [--pgn <file>] \
+//         [--concurrency <n>] [--sprt <elo0> <elo1>]
+//
+// Each engine is a comma separated list of settings: `name`, `weights` (a module written by the tune binary),
+// `contempt`, `hash` (in megabytes), and `time`, `depth` or `nodes` to give it a different limit than the match
+// default. Parts of the search can be turned `on` or `off`: `tt`, `extensions`, `lmr` (late move reductions),
+// `delta` (delta pruning), `nullmove`, `rfp` (reverse futility pruning), `futility`, `lmp` (late move pruning),
+// `pvs` (principal variation search) and `aspiration` (aspiration windows). Every opening is played twice with
+// the colors swapped. Games are adjudicated once both engines agree that one side is winning or that the game is
+// dead drawn. With --sprt the match stops as soon as there is enough evidence that the first engine is at least
+// elo1 stronger than the second (H1) or at most elo0 stronger (H0).
+
+use std::{
+    env, fs,
+    fs::OpenOptions,
+    io::Write,
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use chess::{Board, BoardStatus, ChessMove, Color, Game, Piece};
+use rust_chess::{
+    constants::DEFAULT_HASH_MB,
+    epd::Epd,
+    eval_weights::EvalWeights,
+    game_history::GameHistory,
+    pgn::Pgn,
+    search::{iterative_deepening, Score, SearchOptions},
+    search_limits::{SearchLimits, SystemClock},
+    transposition_table::TranspositionTable,
+};
+
+const DEFAULT_MOVE_TIME_MS: u64 = 100;
+
+// a few well known openings, each a couple of moves in, so that the games don't all go the same way
+const DEFAULT_OPENINGS: [&str; 12] = [
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -",
+    "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq -",
+    "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -",
+    "rnbqkbnr/ppp2ppp/4p3/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq -",
+    "rnbqkbnr/pp2pppp/2p5/3p4/3PP3/8/PPP2PPP/RNBQKBNR w KQkq -",
+    "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR w KQkq -",
+    "rnbqkb1r/ppp1pp1p/3p1np1/8/3PP3/2N5/PPP2PPP/R1BQKBNR w KQkq -",
+    "rnbqkbnr/ppp2ppp/4p3/3p4/2PP4/8/PP2PPPP/RNBQKBNR w KQkq -",
+    "rnbqkbnr/pp2pppp/2p5/3p4/2PP4/8/PP2PPPP/RNBQKBNR w KQkq -",
+    "rnbqkb1r/pppppp1p/5np1/8/2PP4/8/PP2PPPP/RNBQKBNR w KQkq -",
+    "rnbqk2r/pppp1ppp/4pn2/8/1bPP4/2N5/PP2PPPP/R1BQKBNR w KQkq -",
+    "rnbqkbnr/pppp1ppp/8/4p3/2P5/8/PP1PPPPP/RNBQKBNR w KQkq -",
+];
+
+// a side is adjudicated the winner once both engines have seen it this far ahead for this many plies in a row
+const WIN_ADJUDICATION_EVAL: i32 = 1000;
+const WIN_ADJUDICATION_PLIES: usize = 8;
+// and the game is a draw once both have seen it this close to even for this many plies, past the opening
+const DRAW_ADJUDICATION_EVAL: i32 = 10;
+const DRAW_ADJUDICATION_PLIES: usize = 12;
+const DRAW_ADJUDICATION_START_PLY: usize = 80;
+const MAX_GAME_PLIES: usize = 600;
+
+// the chances of accepting H1 when H0 is true, and the other way around
+const SPRT_ALPHA: f64 = 0.05;
+const SPRT_BETA: f64 = 0.05;
+
+struct Engine {
+    name: String,
+    options: SearchOptions,
+    limits: SearchLimits,
+    hash_mb: usize,
+}
+
+struct MatchOptions {
+    engines: [Engine; 2],
+    openings: Vec<Board>,
+    games: usize,
+    concurrency: usize,
+    pgn_path: Option<String>,
+    sprt: Option<(f64, f64)>,
+}
+
+fn parse_engine(
+    spec: &str,
+    default_limits: &SearchLimits,
+    number: usize,
+) -> Result<Engine, String> {
+    let mut engine = Engine {
+        name: format!("engine {}", number),
+        options: SearchOptions::default(),
+        limits: *default_limits,
+        hash_mb: DEFAULT_HASH_MB,
+    };
+    for setting in spec.split(',').filter(|setting| !setting.is_empty()) {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("engine setting {} should look like key=value", setting))?;
+        let invalid = || format!("invalid {} {}", key, value);
+        match key {
+            "name" => engine.name = value.to_string(),
+            "weights" => {
+                let source = fs::read_to_string(value)
+                    .map_err(|error| format!("could not read {}: {}", value, error))?;
+                engine.options.weights = EvalWeights::from_rust_module(&source)
+                    .map_err(|error| format!("could not load {}: {}", value, error))?;
+            }
+            "contempt" => engine.options.contempt = value.parse().map_err(|_| invalid())?,
+            "hash" => engine.hash_mb = value.parse().map_err(|_| invalid())?,
+            "time" => {
+                engine.limits = SearchLimits::move_time(value.parse().map_err(|_| invalid())?)
+            }
+            "depth" => engine.limits = SearchLimits::depth(value.parse().map_err(|_| invalid())?),
+            "nodes" => {
+                engine.limits =
+                    SearchLimits::infinite().with_max_nodes(value.parse().map_err(|_| invalid())?)
+            }
+            _ => {
+                let feature = search_feature(&mut engine.options, key)
+                    .ok_or_else(|| format!("unknown engine setting {}", key))?;
+                *feature = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(invalid()),
+                };
+            }
+        }
+    }
+    Ok(engine)
+}
+
+// the parts of the search that can be switched on and off, to measure what each of them is worth
+fn search_feature<'a>(options: &'a mut SearchOptions, key: &str) -> Option<&'a mut bool> {
+    match key {
+        "tt" => Some(&mut options.transposition_table),
+        "extensions" => Some(&mut options.check_extensions),
+        "lmr" => Some(&mut options.late_move_reductions),
+        "delta" => Some(&mut options.delta_pruning),
+        "nullmove" => Some(&mut options.null_move_pruning),
+        "rfp" => Some(&mut options.reverse_futility_pruning),
+        "futility" => Some(&mut options.futility_pruning),
+        "lmp" => Some(&mut options.late_move_pruning),
+        "pvs" => Some(&mut options.principal_variation_search),
+        "aspiration" => Some(&mut options.aspiration_windows),
+        _ => None,
+    }
+}
+
+fn parse_options() -> Result<MatchOptions, String> {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let mut engine_specs = Vec::new();
+    let mut limits = SearchLimits::move_time(DEFAULT_MOVE_TIME_MS);
+    let mut games = None;
+    let mut openings_path = None;
+    let mut concurrency = 1;
+    let mut pgn_path = None;
+    let mut sprt = None;
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let mut value = || {
+            arguments
+                .next()
+                .ok_or_else(|| format!("{} needs a value", argument))
+        };
+        let number = |text: &String| {
+            text.parse::<f64>()
+                .map_err(|_| format!("invalid {} {}", argument, text))
+        };
+        match argument.as_str() {
+            "--engine" => engine_specs.push(value()?.clone()),
+            "--time" => limits = SearchLimits::move_time(number(value()?)? as u64),
+            "--depth" => limits = SearchLimits::depth(number(value()?)? as u8),
+            "--games" => games = Some(number(value()?)? as usize),
+            "--openings" => openings_path = Some(value()?.clone()),
+            "--concurrency" => concurrency = (number(value()?)? as usize).max(1),
+            "--pgn" => pgn_path = Some(value()?.clone()),
+            "--sprt" => {
+                let elo0 = number(value()?)?;
+                let elo1 = number(value()?)?;
+                if elo0 >= elo1 {
+                    return Err("elo1 has to be bigger than elo0".to_string());
+                }
+                sprt = Some((elo0, elo1));
+            }
+            _ => return Err(format!("unexpected argument {}", argument)),
+        }
+    }
+
+    if engine_specs.len() != 2 {
+        return Err("give exactly two engines to play each other".to_string());
+    }
+    let engines = [
+        parse_engine(&engine_specs[0], &limits, 1)?,
+        parse_engine(&engine_specs[1], &limits, 2)?,
+    ];
+    let openings = match openings_path {
+        Some(path) => read_openings(&path)?,
+        None => DEFAULT_OPENINGS
+            .iter()
+            .map(|fen| Epd::parse(fen).expect("built in openings are valid").board)
+            .collect(),
+    };
+    if openings.is_empty() {
+        return Err("no openings to play".to_string());
+    }
+    Ok(MatchOptions {
+        engines,
+        games: games.unwrap_or(openings.len() * 2),
+        openings,
+        concurrency,
+        pgn_path,
+        sprt,
+    })
+}
+
+fn read_openings(path: &str) -> Result<Vec<Board>, String> {
+    let text =
+        fs::read_to_string(path).map_err(|error| format!("could not read {}: {}", path, error))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(line_index, line)| {
+            Epd::parse(line)
+                .map(|epd| epd.board)
+                .map_err(|error| format!("{} line {}: {}", path, line_index + 1, error))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+struct PlayedGame {
+    index: usize,
+    start_position: Board,
+    moves: Vec<ChessMove>,
+    outcome: Outcome,
+    termination: &'static str,
+}
+
+// neither side can possibly checkmate the other
+fn is_insufficient_material(board: &Board) -> bool {
+    let heavy_pieces =
+        board.pieces(Piece::Pawn) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
+    let minor_pieces = board.pieces(Piece::Knight) | board.pieces(Piece::Bishop);
+    heavy_pieces.popcnt() == 0 && minor_pieces.popcnt() <= 1
+}
+
+fn play_game(
+    index: usize,
+    start_position: Board,
+    white: &Engine,
+    black: &Engine,
+    stop: &AtomicBool,
+) -> PlayedGame {
+    let mut board = start_position;
+    let mut moves = Vec::new();
+    let mut history = GameHistory::default();
+    // each engine keeps its own table for the whole game, like it would in a real match
+    let mut transposition_tables = [
+        TranspositionTable::new(white.hash_mb),
+        TranspositionTable::new(black.hash_mb),
+    ];
+    // what the engines thought of the game after each of their moves, from white's point of view
+    let mut evals: Vec<i32> = Vec::new();
+
+    let (outcome, termination) = loop {
+        match board.status() {
+            BoardStatus::Checkmate if board.side_to_move() == Color::White => {
+                break (Outcome::BlackWins, "checkmate")
+            }
+            BoardStatus::Checkmate => break (Outcome::WhiteWins, "checkmate"),
+            BoardStatus::Stalemate => break (Outcome::Draw, "stalemate"),
+            BoardStatus::Ongoing => {}
+        }
+        if history.repetitions(&board) >= 2 {
+            break (Outcome::Draw, "threefold repetition");
+        }
+        if history.halfmove_clock() >= 100 {
+            break (Outcome::Draw, "fifty move rule");
+        }
+        if is_insufficient_material(&board) {
+            break (Outcome::Draw, "insufficient material");
+        }
+        if moves.len() >= MAX_GAME_PLIES || stop.load(Ordering::Relaxed) {
+            break (Outcome::Draw, "move limit");
+        }
+        if let Some(outcome) = adjudicate(&evals, moves.len()) {
+            break (outcome, "adjudication");
+        }
+
+        let (engine, transposition_table) = if board.side_to_move() == Color::White {
+            (white, &mut transposition_tables[0])
+        } else {
+            (black, &mut transposition_tables[1])
+        };
+        let result = iterative_deepening(
+            &board,
+            &history,
+            &engine.limits,
+            &engine.options,
+            transposition_table,
+            &SystemClock,
+            stop,
+            |_| {},
+        );
+        let Some(chess_move) = result.best_move else {
+            break (Outcome::Draw, "no move");
+        };
+        let eval = match result.score {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) => moves.signum() * WIN_ADJUDICATION_EVAL * 10,
+        };
+        evals.push(if board.side_to_move() == Color::White {
+            eval
+        } else {
+            -eval
+        });
+
+        history.push(&board, chess_move);
+        board = board.make_move_new(chess_move);
+        moves.push(chess_move);
+    };
+
+    PlayedGame {
+        index,
+        start_position,
+        moves,
+        outcome,
+        termination,
+    }
+}
+
+// calls the game once the last few evaluations, which alternate between the two engines, all agree
+fn adjudicate(evals: &[i32], plies: usize) -> Option<Outcome> {
+    let last = |count: usize| evals.len() >= count;
+    if last(WIN_ADJUDICATION_PLIES) {
+        let recent = &evals[evals.len() - WIN_ADJUDICATION_PLIES..];
+        if recent.iter().all(|eval| *eval >= WIN_ADJUDICATION_EVAL) {
+            return Some(Outcome::WhiteWins);
+        }
+        if recent.iter().all(|eval| *eval <= -WIN_ADJUDICATION_EVAL) {
+            return Some(Outcome::BlackWins);
+        }
+    }
+    if plies >= DRAW_ADJUDICATION_START_PLY && last(DRAW_ADJUDICATION_PLIES) {
+        let recent = &evals[evals.len() - DRAW_ADJUDICATION_PLIES..];
+        if recent
+            .iter()
+            .all(|eval| eval.abs() <= DRAW_ADJUDICATION_EVAL)
+        {
+            return Some(Outcome::Draw);
+        }
+    }
+    None
+}
+
+/// Wins, losses and draws from the point of view of the first engine.
+#[derive(Default)]
+struct Tally {
+    wins: u32,
+    losses: u32,
+    draws: u32,
+}
+
+impl Tally {
+    fn games(&self) -> f64 {
+        (self.wins + self.losses + self.draws) as f64
+    }
+
+    fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games()
+    }
+
+    // the variance of the score of a single game
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / self.games()
+    }
+
+    /// The Elo difference between the engines with its 95% confidence interval, once there is enough
+    /// of a mix of results to say anything.
+    fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score();
+        if self.games() < 2.0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+        let margin = 1.96 * (self.variance() / self.games()).sqrt();
+        let lower = elo_from_score((score - margin).max(f64::EPSILON));
+        let upper = elo_from_score((score + margin).min(1.0 - f64::EPSILON));
+        Some((elo_from_score(score), (upper - lower) / 2.0))
+    }
+
+    /// The log likelihood ratio of H1 (the first engine is elo1 stronger) over H0 (it is elo0 stronger),
+    /// using the normal approximation to the distribution of the score.
+    fn log_likelihood_ratio(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0.0 || variance == 0.0 {
+            return 0.0;
+        }
+        let score0 = score_from_elo(elo0);
+        let score1 = score_from_elo(elo1);
+        self.games() * (score1 - score0) * (2.0 * self.score() - score0 - score1) / (2.0 * variance)
+    }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn to_pgn(game: &PlayedGame, white: &Engine, black: &Engine) -> Pgn {
+    let mut replay = Game::new_with_board(game.start_position);
+    for chess_move in &game.moves {
+        replay.make_move(*chess_move);
+    }
+    let mut pgn = Pgn::from_game(&replay, game.start_position, 0, 1);
+    pgn.set_tag("Event", "Self-play match");
+    pgn.set_tag("Round", (game.index + 1).to_string());
+    pgn.set_tag("White", white.name.clone());
+    pgn.set_tag("Black", black.name.clone());
+    pgn.set_tag(
+        "Result",
+        match game.outcome {
+            Outcome::WhiteWins => "1-0",
+            Outcome::BlackWins => "0-1",
+            Outcome::Draw => "1/2-1/2",
+        },
+    );
+    pgn.set_tag("Termination", game.termination);
+    pgn
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!(
+                "usage: selfplay --engine <settings> --engine <settings> [--time <ms> | --depth <plies>] \
+                 [--games <n>] [--openings <epd file>] [--pgn <file>] [--concurrency <n>] [--sprt <elo0> <elo1>]"
+            );
+            process::exit(1);
+        }
+    };
+    let mut pgn_file = options.pgn_path.as_ref().map(|path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|error| {
+                eprintln!("could not open {}: {}", path, error);
+                process::exit(1);
+            })
+    });
+    let [first, second] = &options.engines;
+    // the engine that plays white in a game, and whether it's the first engine
+    let colors = |index: usize| {
+        if index.is_multiple_of(2) {
+            (first, second, true)
+        } else {
+            (second, first, false)
+        }
+    };
+    let sprt_bounds = (
+        (SPRT_BETA / (1.0 - SPRT_ALPHA)).ln(),
+        ((1.0 - SPRT_BETA) / SPRT_ALPHA).ln(),
+    );
+
+    let next_game = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let mut tally = Tally::default();
+    let mut verdict = None;
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..options.concurrency {
+            let sender = sender.clone();
+            let (next_game, stop, options) = (&next_game, &stop, &options);
+            scope.spawn(move || loop {
+                let index = next_game.fetch_add(1, Ordering::Relaxed);
+                if index >= options.games || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let start_position = options.openings[(index / 2) % options.openings.len()];
+                let (white, black, _) = colors(index);
+                let game = play_game(index, start_position, white, black, stop);
+                if sender.send(game).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for game in receiver {
+            if stop.load(Ordering::Relaxed) {
+                // a game cut short by the end of the match doesn't count
+                continue;
+            }
+            let (white, black, first_is_white) = colors(game.index);
+            match (game.outcome, first_is_white) {
+                (Outcome::Draw, _) => tally.draws += 1,
+                (Outcome::WhiteWins, true) | (Outcome::BlackWins, false) => tally.wins += 1,
+                _ => tally.losses += 1,
+            }
+            let pgn = to_pgn(&game, white, black);
+            if let Some(file) = &mut pgn_file {
+                if let Err(error) = writeln!(file, "{}", pgn) {
+                    eprintln!("could not write the game: {}", error);
+                }
+            }
+
+            let mut line = format!(
+                "game {:>4}: {} - {} {} ({}) | {} +{} -{} ={}",
+                game.index + 1,
+                white.name,
+                black.name,
+                pgn.tag("Result").unwrap_or("*"),
+                game.termination,
+                first.name,
+                tally.wins,
+                tally.losses,
+                tally.draws
+            );
+            if let Some((elo, error)) = tally.elo() {
+                line.push_str(&format!(" | elo {:+.1} +/- {:.1}", elo, error));
+            }
+            if let Some((elo0, elo1)) = options.sprt {
+                let llr = tally.log_likelihood_ratio(elo0, elo1);
+                line.push_str(&format!(
+                    " | llr {:.2} ({:.2}, {:.2})",
+                    llr, sprt_bounds.0, sprt_bounds.1
+                ));
+                if llr >= sprt_bounds.1 {
+                    verdict = Some(format!(
+                        "H1 accepted: {} is stronger by at least {} elo",
+                        first.name, elo1
+                    ));
+                } else if llr <= sprt_bounds.0 {
+                    verdict = Some(format!(
+                        "H0 accepted: {} is not stronger by {} elo",
+                        first.name, elo1
+                    ));
+                }
+                if verdict.is_some() {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+            println!("{}", line);
+        }
+    });
+
+    println!();
+    println!(
+        "{} vs {}: +{} -{} ={} ({:.1}%)",
+        first.name,
+        second.name,
+        tally.wins,
+        tally.losses,
+        tally.draws,
+        tally.score() * 100.0
+    );
+    match tally.elo() {
+        Some((elo, error)) => println!("elo difference {:+.1} +/- {:.1}", elo, error),
+        None => println!("not enough decisive results to estimate the elo difference"),
+    }
+    match verdict {
+        Some(verdict) => println!("{}", verdict),
+        None if options.sprt.is_some() => println!("SPRT inconclusive, play more games"),
+        None => {}
+    }
+}
@@ -0,0 +1,186 @@
+// Tunes the evaluation weights with Texel's method: find the weights whose evaluations, squashed through a
+// sigmoid, best predict the results of the games a set of positions was taken from.
+//
+//     cargo run --release --bin tune -- <positions file> [output file]
+//
+// Every line of the positions file is a FEN or EPD position followed by the result of its game, either as
+// a PGN result (1-0, 0-1, 1/2-1/2, on its own or in an EPD opcode like c9 "1-0";) or as [1.0], [0.5] or
+// [0.0]. The tuned weights are written out as a new weights module, src/weights.rs unless told otherwise.
+
+use std::{env, fs, process, thread};
+
+use chess::{Board, Color};
+use rust_chess::{
+    eval_weights::EvalWeights,
+    evaluation::{board_eval, board_eval_with_weights},
+    fen::board_from_fen,
+    search::quiescence_eval,
+    weights::WEIGHTS,
+};
+
+const DEFAULT_OUTPUT_PATH: &str = "src/weights.rs";
+
+struct Position {
+    board: Board,
+    /// the result of the game from white's point of view: 1 for a win, 0.5 for a draw and 0 for a loss
+    result: f64,
+}
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let Some(positions_path) = arguments.first() else {
+        eprintln!("usage: tune <positions file> [output file]");
+        process::exit(1);
+    };
+    let output_path = arguments.get(1).map_or(DEFAULT_OUTPUT_PATH, String::as_str);
+    let text = match fs::read_to_string(positions_path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("could not read {}: {}", positions_path, error);
+            process::exit(1);
+        }
+    };
+
+    let positions = load_positions(&text);
+    if positions.is_empty() {
+        eprintln!("no quiet positions with results in {}", positions_path);
+        process::exit(1);
+    }
+    let k = find_scaling_constant(&positions);
+    println!("scaling constant K = {:.4}", k);
+    tune(&positions, k, WEIGHTS.clone(), output_path);
+}
+
+// reads every position with a result, keeping only the quiet ones. a position where there are captures
+// to be made says more about the exchange than about the evaluation
+fn load_positions(text: &str) -> Vec<Position> {
+    let mut positions = Vec::new();
+    let mut unreadable = 0;
+    let mut not_quiet = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let Some(position) = parse_position(line) else {
+            unreadable += 1;
+            continue;
+        };
+        let color_multiplier = if position.board.side_to_move() == Color::White {
+            1
+        } else {
+            -1
+        };
+        if position.board.checkers().popcnt() > 0
+            || quiescence_eval(&position.board) != board_eval(&position.board) * color_multiplier
+        {
+            not_quiet += 1;
+            continue;
+        }
+        positions.push(position);
+    }
+    println!(
+        "{} quiet positions, skipped {} that weren't quiet and {} that couldn't be read",
+        positions.len(),
+        not_quiet,
+        unreadable
+    );
+    positions
+}
+
+fn parse_position(line: &str) -> Option<Position> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    // EPD leaves out the move counters, which don't matter to the evaluation anyway
+    let board = board_from_fen(&format!("{} 0 1", fields[..4].join(" "))).ok()?;
+    let rest = fields[4..].join(" ");
+    let result = if rest.contains("1/2-1/2") || rest.contains("[0.5]") {
+        0.5
+    } else if rest.contains("1-0") || rest.contains("[1.0]") || rest.contains("[1]") {
+        1.0
+    } else if rest.contains("0-1") || rest.contains("[0.0]") || rest.contains("[0]") {
+        0.0
+    } else {
+        return None;
+    };
+    Some(Position { board, result })
+}
+
+// the expected result of the game for white, given the evaluation
+fn sigmoid(eval: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+fn mean_squared_error(positions: &[Position], weights: &EvalWeights, k: f64) -> f64 {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = positions.len().div_ceil(threads).max(1);
+    let total_error: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|position| {
+                            let eval = board_eval_with_weights(&position.board, weights);
+                            (position.result - sigmoid(eval, k)).powi(2)
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("error thread panicked"))
+            .sum()
+    });
+    total_error / positions.len() as f64
+}
+
+// the K that makes the current weights fit the results best, so that the tuning doesn't just rescale them
+fn find_scaling_constant(positions: &[Position]) -> f64 {
+    let (mut low, mut high) = (0.0, 4.0);
+    for _ in 0..40 {
+        let third = (high - low) / 3.0;
+        if mean_squared_error(positions, &WEIGHTS, low + third)
+            < mean_squared_error(positions, &WEIGHTS, high - third)
+        {
+            high -= third;
+        } else {
+            low += third;
+        }
+    }
+    (low + high) / 2.0
+}
+
+// nudges every weight up or down by one for as long as that lowers the error, saving after every pass
+fn tune(positions: &[Position], k: f64, mut weights: EvalWeights, output_path: &str) {
+    let parameter_count = weights.parameters_mut().len();
+    let mut best_error = mean_squared_error(positions, &weights, k);
+    println!(
+        "{} weights, starting error {:.6}",
+        parameter_count, best_error
+    );
+
+    for iteration in 1.. {
+        let mut improved = false;
+        for index in 0..parameter_count {
+            for step in [1, -1] {
+                *weights.parameters_mut()[index] += step;
+                let error = mean_squared_error(positions, &weights, k);
+                if error < best_error {
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+                *weights.parameters_mut()[index] -= step;
+            }
+        }
+        println!("iteration {}: error {:.6}", iteration, best_error);
+        if let Err(error) = fs::write(output_path, weights.to_rust_module()) {
+            eprintln!("could not write {}: {}", output_path, error);
+        }
+        if !improved {
+            break;
+        }
+    }
+    println!("done, the tuned weights are in {}", output_path);
+}
@@ -0,0 +1,17 @@
+use std::io::BufRead;
+
+use rust_chess::uci::UciEngine;
+
+fn main() {
+    let mut engine = UciEngine::new();
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.handle_command(&line) {
+            return;
+        }
+    }
+    // stdin was closed without a quit command, so make sure any running search finishes cleanly
+    engine.handle_command("quit");
+}
@@ -1,12 +1,25 @@
 use chess::Piece;
 
-pub const MAX_DEPTH: u8 = 8;
+// the deepest iterative deepening will ever go, no matter how much time it has
+pub const MAX_SEARCH_DEPTH: u8 = 64;
+
+// how long the ai thinks about a move in the browser
+pub const AI_MOVE_TIME_MS: u64 = 2000;
+
+// the size of the transposition table in megabytes, unless told otherwise
+pub const DEFAULT_HASH_MB: usize = 16;
+
+// leave some time on the clock for communication overhead with whoever asked us for a move
+pub const MOVE_OVERHEAD_MS: u64 = 50;
 
 pub const CHECKMATE_EVAL: i32 = 1_000_000;
 
+// how much worse than an even position the ai thinks a draw is, so that it plays on when the game is level
+pub const DEFAULT_CONTEMPT: i32 = 10;
+
 pub const MAX_EXTENSIONS: u8 = 3;
 
-pub const PIECES: &'static [Piece] = &[
+pub const PIECES: &[Piece] = &[
     Piece::Pawn,
     Piece::Knight,
     Piece::Bishop,
@@ -15,131 +28,23 @@ pub const PIECES: &'static [Piece] = &[
     Piece::King,
 ];
 
-pub const ENDGAME_PLY: u32 = 50;
-pub const MIDGAME_PLY: u32 = 12;
-
-pub const CASTLED_BONUS: i16 = 150; // 150
+// how much each piece counts towards the game phase. with all of them on the board it is the middlegame,
+// and with none of them it is the endgame
+pub const KNIGHT_PHASE: i32 = 1;
+pub const BISHOP_PHASE: i32 = 1;
+pub const ROOK_PHASE: i32 = 2;
+pub const QUEEN_PHASE: i32 = 4;
+pub const TOTAL_PHASE: i32 = 4 * KNIGHT_PHASE + 4 * BISHOP_PHASE + 4 * ROOK_PHASE + 2 * QUEEN_PHASE;
 
-pub const NUM_COLUMNS: usize = 8;
+// the game phase of the starting position. the endgame is 0
+pub const MAX_PHASE: i32 = 256;
 
 pub const MAX_KILLER_MOVE_PLY: usize = 32; // 2^5
 
-// Inspired by Sebastian Lague's piece evaluation grids
-pub const ENDGAME_INDEX_START: usize = 6; // pawns at start of game look at index [0] for piece position, the look at 6 for endgame
-
-pub const WHITE_PIECE_POSITIONS: [[i16; 64]; 8] = [
-    PAWNS, KNIGHTS, BISHOPS, ROOKS, QUEENS, KINGS, PAWNS_END, KINGS_END,
-];
-
-pub const BLACK_PIECE_POSITIONS: [[i16; 64]; 8] = [
-    BLACK_PAWNS,
-    BLACK_KNIGHTS,
-    BLACK_BISHOPS,
-    BLACK_ROOKS,
-    BLACK_QUEENS,
-    BLACK_KINGS,
-    BLACK_PAWNS_END,
-    BLACK_KINGS_END,
-];
-
-pub const PAWNS: [i16; 64] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, -20, -20, 10, 10, 5, 5, -5, -10, 0, 0, -10, -5, 5, 0, 0, 0,
-    20, 20, 0, 0, 0, 5, 5, 10, 25, 25, 10, 5, 5, 10, 10, 20, 30, 30, 20, 10, 10, 50, 50, 50, 50,
-    50, 50, 50, 50, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-
-pub const BLACK_PAWNS: [i16; 64] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 50, 50, 50, 50, 50, 50, 50, 50, 10, 10, 20, 30, 30, 20, 10, 10, 5, 5,
-    10, 25, 25, 10, 5, 5, 0, 0, 0, 20, 20, 0, 0, 0, 5, -5, -10, 0, 0, -10, -5, 5, 5, 10, 10, -20,
-    -20, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-
-pub const PAWNS_END: [i16; 64] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 20, 20,
-    20, 20, 20, 20, 20, 20, 30, 30, 30, 30, 30, 30, 30, 30, 50, 50, 50, 50, 50, 50, 50, 50, 80, 80,
-    80, 80, 80, 80, 80, 80, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-
-pub const BLACK_PAWNS_END: [i16; 64] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 80, 80, 80, 80, 80, 80, 80, 80, 50, 50, 50, 50, 50, 50, 50, 50, 30, 30,
-    30, 30, 30, 30, 30, 30, 20, 20, 20, 20, 20, 20, 20, 20, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10,
-    10, 10, 10, 10, 10, 10, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-
-pub const ROOKS: [i16; 64] = [
-    0, 0, 0, 5, 5, 0, 0, 0, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0,
-    0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 5, 10, 10, 10, 10, 10, 10, 5, 0, 0,
-    0, 0, 0, 0, 0, 0,
-];
-
-pub const BLACK_ROOKS: [i16; 64] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, 10, 10, 10, 10, 5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0,
-    0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 0, 0,
-    0, 5, 5, 0, 0, 0,
-];
-
-pub const KNIGHTS: [i16; 64] = [
-    -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 5, 5, 0, -20, -40, -30, 5, 10, 15, 15, 10,
-    5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 10, 15, 15, 10,
-    0, -30, -40, -20, 0, 0, 0, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
-];
-
-pub const BLACK_KNIGHTS: [i16; 64] = [
-    -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15, 10,
-    0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 10, 15, 15, 10,
-    5, -30, -40, -20, 0, 5, 5, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
-];
-
-pub const BISHOPS: [i16; 64] = [
-    -20, -10, -15, -10, -10, -15, -10, -20, -10, 5, 0, 0, 0, 0, 5, -10, -10, 10, 10, 10, 10, 10,
-    10, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 5, 10, 10, 5, 0,
-    -10, -10, 0, 0, 0, 0, 0, 0, -10, -20, -10, -10, -10, -10, -10, -10, -20,
-];
-
-pub const BLACK_BISHOPS: [i16; 64] = [
-    -20, -10, -10, -10, -10, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 10, 10, 5, 0,
-    -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 10, 10, 10, 10, 10, 10,
-    -10, -10, 5, 0, 0, 0, 0, 5, -10, -20, -10, -15, -10, -10, -15, -10, -20,
-];
-
-pub const QUEENS: [i16; 64] = [
-    -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 5, 0, 0, 0, 0, -10, -10, 5, 5, 5, 5, 5, 0, -10,
-    0, 0, 5, 5, 5, 5, 0, -5, -5, 0, 5, 5, 5, 5, 0, -5, -10, 0, 5, 5, 5, 5, 0, -10, -10, 0, 0, 0, 0,
-    0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
-];
-
-pub const BLACK_QUEENS: [i16; 64] = [
-    -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 5, 5, 5, 0, -10,
-    -5, 0, 5, 5, 5, 5, 0, -5, 0, 0, 5, 5, 5, 5, 0, -5, -10, 5, 5, 5, 5, 5, 0, -10, -10, 0, 5, 0, 0,
-    0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
-];
-
-pub const KINGS: [i16; 64] = [
-    // 20, 30, 10, 0, 0, 10, 30, 20, 20, 20, -5, -5, -5, -5, 20, 20, -10, -20, -20, -20, -20, -20,
-    // -20, -10, -20, -30, -30, -40, -40, -30, -30, -20, -30, -40, -40, -50, -50, -40, -40, -30, -40,
-    // -50, -50, -60, -60, -50, -50, -40, -60, -60, -60, -60, -60, -60, -60, -60, -80, -70, -70, -70,
-    // -70, -70, -70, -80,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
+// the number of moves a knight, bishop, rook or queen has on an average square. having fewer than this
+// counts against it
+pub const MOBILITY_BASELINE: [i32; 4] = [4, 6, 6, 12];
 
-pub const BLACK_KINGS: [i16; 64] = [
-    // -80, -70, -70, -70, -70, -70, -70, -80, -60, -60, -60, -60, -60, -60, -60, -60, -40, -50, -50,
-    // -60, -60, -50, -50, -40, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40, -40, -30,
-    // -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, -5, -5, -5, -5, 20, 20, 20, 30, 10,
-    // 0, 0, 10, 30, 20,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-
-pub const KINGS_END: [i16; 64] = [
-    -50, -30, -30, -30, -30, -30, -30, -50, -30, -25, 0, 0, 0, 0, -25, -30, -25, -20, 20, 25, 25,
-    20, -20, -25, -20, -15, 30, 40, 40, 30, -15, -20, -15, -10, 35, 45, 45, 35, -10, -15, -10, -5,
-    20, 30, 30, 20, -5, -10, -5, 0, 5, 5, 5, 5, 0, -5, -20, -10, -10, -10, -10, -10, -10, -20,
-];
-
-pub const BLACK_KINGS_END: [i16; 64] = [
-    -20, -10, -10, -10, -10, -10, -10, -20, -5, 0, 5, 5, 5, 5, 0, -5, -10, -5, 20, 30, 30, 20, -5,
-    -10, -15, -10, 35, 45, 45, 35, -10, -15, -20, -15, 30, 40, 40, 30, -15, -20, -25, -20, 20, 25,
-    25, 20, -20, -25, -30, -25, 0, 0, 0, 0, -25, -30, -50, -30, -30, -30, -30, -30, -30, -50,
-];
+// the attack units on a king are squared so that piling up attackers gets dangerous quickly, then divided by this
+pub const KING_ATTACK_DIVISOR: i32 = 4;
+pub const MAX_KING_ATTACK_PENALTY: i32 = 500;
@@ -0,0 +1,147 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use chess::{Board, ChessMove};
+
+use crate::san::san_to_move;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpdError {
+    pub message: String,
+}
+
+impl EpdError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for EpdError {}
+
+/// One line of an EPD file: a position followed by operations like `bm Qg6; id "WAC.001";`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Epd {
+    pub board: Board,
+    /// every operation in the order it appears, as an opcode and its operands, with any quotes taken off
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let mut rest = line.trim();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            let (field, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if field.is_empty() {
+                return Err(EpdError::new("an EPD position needs four fields"));
+            }
+            fields.push(field);
+            rest = remainder.trim_start();
+        }
+        // the chess crate panics rather than returning an error when a king is missing
+        for king in ['K', 'k'] {
+            if fields[0].matches(king).count() != 1 {
+                return Err(EpdError::new("each side needs exactly one king"));
+            }
+        }
+        // the move counters are left out of EPD, and don't matter for anything we use it for
+        let board = Board::from_str(&format!("{} 0 1", fields.join(" ")))
+            .map_err(|error| EpdError::new(format!("invalid position: {}", error)))?;
+
+        let operations = split_operations(rest)?
+            .into_iter()
+            .filter_map(|mut operands| {
+                if operands.is_empty() {
+                    None
+                } else {
+                    let opcode = operands.remove(0);
+                    Some((opcode, operands))
+                }
+            })
+            .collect();
+        Ok(Epd { board, operations })
+    }
+
+    /// The operands of the first operation with this opcode.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// The moves given for an opcode like `bm` (best move) or `am` (avoid move). They are normally in
+    /// SAN, but coordinate notation like e2e4 works too.
+    pub fn moves(&self, opcode: &str) -> Result<Vec<ChessMove>, EpdError> {
+        let Some(operands) = self.operation(opcode) else {
+            return Ok(Vec::new());
+        };
+        operands
+            .iter()
+            .map(|operand| {
+                san_to_move(&self.board, operand)
+                    .or_else(|| {
+                        ChessMove::from_str(operand)
+                            .ok()
+                            .filter(|chess_move| self.board.legal(*chess_move))
+                    })
+                    .ok_or_else(|| {
+                        EpdError::new(format!("{} {} isn't a legal move", opcode, operand))
+                    })
+            })
+            .collect()
+    }
+
+    /// The `id` of the position, which test suites use to name it.
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id")
+            .and_then(|operands| operands.first())
+            .map(String::as_str)
+    }
+}
+
+// splits the operations on semicolons and their words on whitespace, keeping quoted strings together
+fn split_operations(text: &str) -> Result<Vec<Vec<String>>, EpdError> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ';' {
+            chars.next();
+            operations.push(std::mem::take(&mut words));
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut word = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => word.push(c),
+                    None => return Err(EpdError::new("unterminated string")),
+                }
+            }
+            words.push(word);
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == ';' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    // the last operation is allowed to leave out its semicolon
+    operations.push(words);
+    Ok(operations)
+}
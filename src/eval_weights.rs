@@ -0,0 +1,261 @@
+use std::{error::Error, fmt, fmt::Write};
+
+use crate::weights::WEIGHTS;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightsError {
+    pub message: String,
+}
+
+impl WeightsError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for WeightsError {}
+
+/// Every number the evaluation is built from, so that they can be tuned together. Pairs are
+/// (middlegame, endgame) and arrays of pieces go knight, bishop, rook, queen unless noted otherwise.
+/// The engine uses `weights::WEIGHTS`, which the `tune` binary regenerates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalWeights {
+    /// pawn, knight, bishop, rook and queen. the king is priceless
+    pub piece_values: [i32; 5],
+    pub no_castle_rights_penalty: i32,
+    /// indexed by piece and then by square from white's point of view, so a1 comes first and h8 last.
+    /// black uses the same tables flipped vertically
+    pub midgame_piece_positions: [[i32; 64]; 6],
+    pub endgame_piece_positions: [[i32; 64]; 6],
+
+    pub isolated_pawn_penalty: (i32, i32),
+    pub doubled_pawn_penalty: (i32, i32),
+    pub backward_pawn_penalty: (i32, i32),
+    /// for every group of pawns on adjacent files past the first one
+    pub pawn_island_penalty: (i32, i32),
+    /// indexed by how far up the board the pawn is from its own side, so the second rank comes second
+    pub passed_pawn_bonus: [(i32, i32); 8],
+    /// a pawn defended by another pawn, by rank like `passed_pawn_bonus`
+    pub supported_pawn_bonus: [(i32, i32); 8],
+    /// a pawn with another pawn next to it on the same rank, by rank like `passed_pawn_bonus`
+    pub phalanx_pawn_bonus: [(i32, i32); 8],
+
+    /// how much every square a piece can move to is worth
+    pub mobility_bonus: [(i32, i32); 4],
+    pub bishop_pair_bonus: (i32, i32),
+    /// a knight deep in enemy territory that is defended by a pawn and can't be chased away by one
+    pub knight_outpost_bonus: (i32, i32),
+    pub rook_open_file_bonus: (i32, i32),
+    pub rook_semi_open_file_bonus: (i32, i32),
+    pub rook_on_seventh_bonus: (i32, i32),
+
+    /// how many attack units every square next to the enemy king that a piece attacks is worth. king
+    /// safety only matters in the middlegame, so it has no endgame part
+    pub king_attack_weight: [i32; 4],
+    /// for each pawn in front of the king, on the rank right in front of it and the one after that
+    pub pawn_shield_bonus: [i32; 2],
+    /// for the king's file and each file next to it without a pawn of our own
+    pub king_semi_open_file_penalty: i32,
+    /// on top of `king_semi_open_file_penalty` when there are no enemy pawns on the file either
+    pub king_open_file_penalty: i32,
+}
+
+impl EvalWeights {
+    /// Every weight as one flat vector, for the tuner to adjust one at a time.
+    pub fn parameters_mut(&mut self) -> Vec<&mut i32> {
+        let mut parameters: Vec<&mut i32> = Vec::new();
+        parameters.extend(self.piece_values.iter_mut());
+        parameters.push(&mut self.no_castle_rights_penalty);
+        for table in self
+            .midgame_piece_positions
+            .iter_mut()
+            .chain(self.endgame_piece_positions.iter_mut())
+        {
+            parameters.extend(table.iter_mut());
+        }
+        for (midgame, endgame) in [
+            &mut self.isolated_pawn_penalty,
+            &mut self.doubled_pawn_penalty,
+            &mut self.backward_pawn_penalty,
+            &mut self.pawn_island_penalty,
+        ]
+        .into_iter()
+        .chain(self.passed_pawn_bonus.iter_mut())
+        .chain(self.supported_pawn_bonus.iter_mut())
+        .chain(self.phalanx_pawn_bonus.iter_mut())
+        .chain(self.mobility_bonus.iter_mut())
+        .chain([
+            &mut self.bishop_pair_bonus,
+            &mut self.knight_outpost_bonus,
+            &mut self.rook_open_file_bonus,
+            &mut self.rook_semi_open_file_bonus,
+            &mut self.rook_on_seventh_bonus,
+        ]) {
+            parameters.push(midgame);
+            parameters.push(endgame);
+        }
+        parameters.extend(self.king_attack_weight.iter_mut());
+        parameters.extend(self.pawn_shield_bonus.iter_mut());
+        parameters.push(&mut self.king_semi_open_file_penalty);
+        parameters.push(&mut self.king_open_file_penalty);
+        parameters
+    }
+
+    /// Reads the weights back out of a module written by `to_rust_module`, so that weights can be tried out
+    /// without building them into the engine.
+    pub fn from_rust_module(source: &str) -> Result<EvalWeights, WeightsError> {
+        let start = source
+            .find("EvalWeights {")
+            .ok_or_else(|| WeightsError::new("no EvalWeights in the module"))?;
+        let code: String = source[start..]
+            .lines()
+            .map(|line| line.split("//").next().unwrap_or(""))
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        // the fields are written in the same order as the parameters, and none of their names have digits
+        let mut values = Vec::new();
+        let mut chars = code.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            if c == '-' || c.is_ascii_digit() {
+                let mut end = index + c.len_utf8();
+                while let Some((next_index, _)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    end = next_index + 1;
+                }
+                if let Ok(value) = code[index..end].parse::<i32>() {
+                    values.push(value);
+                }
+            }
+        }
+
+        let mut weights = WEIGHTS.clone();
+        let mut parameters = weights.parameters_mut();
+        if values.len() != parameters.len() {
+            return Err(WeightsError::new(format!(
+                "expected {} weights but found {}",
+                parameters.len(),
+                values.len()
+            )));
+        }
+        for (parameter, value) in parameters.iter_mut().zip(values) {
+            **parameter = value;
+        }
+        Ok(weights)
+    }
+
+    /// The source of a `weights` module that defines `WEIGHTS` as these weights.
+    pub fn to_rust_module(&self) -> String {
+        let mut source = String::new();
+        source.push_str(
+            "// Generated by the tune binary (src/bin/tune.rs). Rather than editing the numbers by hand, run\n\
+             // `cargo run --release --bin tune -- <positions file>` to tune them against a set of positions.\n\
+             use crate::eval_weights::EvalWeights;\n\
+             \n\
+             /// The weights the engine evaluates positions with.\n\
+             #[rustfmt::skip]\n\
+             pub const WEIGHTS: EvalWeights = EvalWeights {\n",
+        );
+        let mut field = |name: &str, value: String| {
+            let _ = writeln!(source, "    {}: {},", name, value);
+        };
+        field("piece_values", format_array(&self.piece_values));
+        field(
+            "no_castle_rights_penalty",
+            self.no_castle_rights_penalty.to_string(),
+        );
+        field(
+            "midgame_piece_positions",
+            format_tables(&self.midgame_piece_positions),
+        );
+        field(
+            "endgame_piece_positions",
+            format_tables(&self.endgame_piece_positions),
+        );
+        field(
+            "isolated_pawn_penalty",
+            format_pair(self.isolated_pawn_penalty),
+        );
+        field(
+            "doubled_pawn_penalty",
+            format_pair(self.doubled_pawn_penalty),
+        );
+        field(
+            "backward_pawn_penalty",
+            format_pair(self.backward_pawn_penalty),
+        );
+        field("pawn_island_penalty", format_pair(self.pawn_island_penalty));
+        field("passed_pawn_bonus", format_pairs(&self.passed_pawn_bonus));
+        field(
+            "supported_pawn_bonus",
+            format_pairs(&self.supported_pawn_bonus),
+        );
+        field("phalanx_pawn_bonus", format_pairs(&self.phalanx_pawn_bonus));
+        field("mobility_bonus", format_pairs(&self.mobility_bonus));
+        field("bishop_pair_bonus", format_pair(self.bishop_pair_bonus));
+        field(
+            "knight_outpost_bonus",
+            format_pair(self.knight_outpost_bonus),
+        );
+        field(
+            "rook_open_file_bonus",
+            format_pair(self.rook_open_file_bonus),
+        );
+        field(
+            "rook_semi_open_file_bonus",
+            format_pair(self.rook_semi_open_file_bonus),
+        );
+        field(
+            "rook_on_seventh_bonus",
+            format_pair(self.rook_on_seventh_bonus),
+        );
+        field("king_attack_weight", format_array(&self.king_attack_weight));
+        field("pawn_shield_bonus", format_array(&self.pawn_shield_bonus));
+        field(
+            "king_semi_open_file_penalty",
+            self.king_semi_open_file_penalty.to_string(),
+        );
+        field(
+            "king_open_file_penalty",
+            self.king_open_file_penalty.to_string(),
+        );
+        source.push_str("};\n");
+        source
+    }
+}
+
+fn format_array(values: &[i32]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn format_pair((midgame, endgame): (i32, i32)) -> String {
+    format!("({}, {})", midgame, endgame)
+}
+
+fn format_pairs(pairs: &[(i32, i32)]) -> String {
+    let pairs: Vec<String> = pairs.iter().map(|pair| format_pair(*pair)).collect();
+    format!("[{}]", pairs.join(", "))
+}
+
+// piece square tables laid out as 8x8 grids, one rank per line
+fn format_tables(tables: &[[i32; 64]; 6]) -> String {
+    let mut source = String::from("[\n");
+    for table in tables {
+        source.push_str("        [\n");
+        for rank in table.chunks(8) {
+            let squares: Vec<String> = rank.iter().map(|value| format!("{:4}", value)).collect();
+            let _ = writeln!(source, "           {},", squares.join(","));
+        }
+        source.push_str("        ],\n");
+    }
+    source.push_str("    ]");
+    source
+}
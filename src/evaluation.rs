@@ -1,113 +1,268 @@
 use crate::constants::{
-    BLACK_PIECE_POSITIONS, CASTLED_BONUS, ENDGAME_INDEX_START, ENDGAME_PLY, MIDGAME_PLY,
-    NUM_COLUMNS, PIECES, WHITE_PIECE_POSITIONS,
+    BISHOP_PHASE, KING_ATTACK_DIVISOR, KNIGHT_PHASE, MAX_KING_ATTACK_PENALTY, MAX_PHASE,
+    MOBILITY_BASELINE, PIECES, QUEEN_PHASE, ROOK_PHASE, TOTAL_PHASE,
 };
-use chess::{Board, CastleRights, Color, Piece, Square};
-
-pub fn board_eval(board: &Board, move_ply: u32) -> i32 {
-    let color = board.side_to_move();
-    let mut eval = 0;
-    let material_count = count_material(board) * 2;
-    let king_safety = evaluate_king_safety(board, color, move_ply);
-    let castle_status = can_castle(board, color, move_ply);
-    let piece_positions = piece_positions(board, color, move_ply) * 2;
-    eval += material_count + king_safety + castle_status + piece_positions;
-
-    return eval as i32;
+use crate::eval_weights::EvalWeights;
+use crate::pawn_structure::{
+    evaluate_pawn_structure, passed_pawns, pawn_attacks, ranks_ahead, relative_rank, PawnHashTable,
+    PawnStructure,
+};
+use crate::weights::WEIGHTS;
+use chess::{
+    get_adjacent_files, get_bishop_moves, get_file, get_king_moves, get_knight_moves, get_rank,
+    get_rook_moves, BitBoard, Board, CastleRights, Color, File, Piece, Square, EMPTY,
+};
+
+/// The evaluation of the board from white's point of view.
+pub fn board_eval(board: &Board) -> i32 {
+    board_eval_with_weights(board, &WEIGHTS)
+}
+
+/// Same as `board_eval`, but with weights other than the ones the engine uses. This is what the tuner
+/// tries its changes out with.
+pub fn board_eval_with_weights(board: &Board, weights: &EvalWeights) -> i32 {
+    evaluate(board, weights, &evaluate_pawn_structure(board, weights))
+}
+
+/// Same as `board_eval_with_weights`, but looks the pawn structure up in `pawn_table` instead of working
+/// it out every time.
+pub(crate) fn board_eval_with_pawn_table(
+    board: &Board,
+    weights: &EvalWeights,
+    pawn_table: &mut PawnHashTable,
+) -> i32 {
+    evaluate(board, weights, &pawn_table.get_or_evaluate(board, weights))
+}
+
+fn evaluate(board: &Board, weights: &EvalWeights, pawn_structure: &PawnStructure) -> i32 {
+    let phase = game_phase(board);
+    let (passed_midgame, passed_endgame) = passed_pawns(board, weights, pawn_structure.passed);
+    let mut midgame = pawn_structure.midgame + passed_midgame;
+    let mut endgame = pawn_structure.endgame + passed_endgame;
+    for color in [Color::White, Color::Black] {
+        let color_multiplier = if color == Color::White { 1 } else { -1 };
+        // a king that is safe from attack only matters while there are pieces left to attack it
+        midgame += (evaluate_king_safety(board, weights, color)
+            + can_castle(board, weights, color))
+            * color_multiplier;
+        let (midgame_positions, endgame_positions) = piece_positions(board, weights, color);
+        midgame += midgame_positions * 2 * color_multiplier;
+        endgame += endgame_positions * 2 * color_multiplier;
+        let (midgame_activity, endgame_activity) = piece_activity(board, weights, color);
+        midgame += midgame_activity * color_multiplier;
+        endgame += endgame_activity * color_multiplier;
+    }
+    let material_count = count_material(board, weights) * 2;
+
+    material_count + taper(midgame, endgame, phase)
+}
+
+/// How far from the endgame the position is, judging by the pieces left on the board. This is
+/// `MAX_PHASE` with every piece still there and 0 with only kings and pawns left.
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = [
+        (Piece::Knight, KNIGHT_PHASE),
+        (Piece::Bishop, BISHOP_PHASE),
+        (Piece::Rook, ROOK_PHASE),
+        (Piece::Queen, QUEEN_PHASE),
+    ]
+    .iter()
+    .map(|(piece, piece_phase)| board.pieces(*piece).popcnt() as i32 * piece_phase)
+    .sum();
+    // promotions can put more pieces on the board than there were at the start
+    (phase.min(TOTAL_PHASE) * MAX_PHASE + TOTAL_PHASE / 2) / TOTAL_PHASE
+}
+
+/// Blends a middlegame and an endgame evaluation according to the game phase.
+pub fn taper(midgame: i32, endgame: i32, phase: i32) -> i32 {
+    (midgame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE
 }
 
-fn count_material(board: &Board) -> i16 {
+// the king is on the board no matter what, so only the other pieces count
+fn count_material(board: &Board, weights: &EvalWeights) -> i32 {
     let mut material = 0;
-    for piece in PIECES {
+    for (piece, value) in PIECES.iter().zip(weights.piece_values) {
         let piece_bb = board.pieces(*piece);
-        material += ((piece_bb & board.color_combined(Color::White)).popcnt()
-            * get_count_of_piece(*piece)) as i16;
-        material -= ((piece_bb & board.color_combined(Color::Black)).popcnt()
-            * get_count_of_piece(*piece)) as i16;
+        material += (piece_bb & board.color_combined(Color::White)).popcnt() as i32 * value;
+        material -= (piece_bb & board.color_combined(Color::Black)).popcnt() as i32 * value;
     }
     material
 }
 
 pub fn get_count_of_piece(piece: Piece) -> u32 {
+    get_count_of_piece_with_weights(piece, &WEIGHTS)
+}
+
+/// Same as `get_count_of_piece`, but with the piece values of `weights`.
+pub fn get_count_of_piece_with_weights(piece: Piece, weights: &EvalWeights) -> u32 {
     match piece {
-        Piece::Pawn => 100,
-        Piece::Knight => 300,
-        Piece::Bishop => 320,
-        Piece::Rook => 500,
-        Piece::Queen => 900,
         Piece::King => 10000,
+        _ => weights.piece_values[piece.to_index()] as u32,
     }
 }
 
-fn evaluate_king_safety(board: &Board, color: Color, move_ply: u32) -> i16 {
+// whether the king of the given color has castled, so it is tucked away behind its pawns
+fn is_castled(board: &Board, color: Color) -> bool {
+    let back_rank = color.to_my_backrank();
     let king_square = board.king_square(color);
-    if move_ply >= ENDGAME_PLY {
-        return 0; // no longer care about king safety
+    if king_square.get_rank() != back_rank {
+        return false;
     }
-    fn is_rook_not_on_square(board: &Board, square: Square) -> bool {
-        return board.piece_on(square).is_none() || board.piece_on(square).unwrap() != Piece::Rook;
+    let is_rook_not_on_file =
+        |file: File| board.piece_on(Square::make_square(back_rank, file)) != Some(Piece::Rook);
+
+    match king_square.get_file() {
+        File::G | File::H => is_rook_not_on_file(File::H),
+        File::A | File::B | File::C => is_rook_not_on_file(File::A) && is_rook_not_on_file(File::B),
+        _ => false,
     }
+}
 
-    // if white is castled
-    if ((king_square == Square::G1 || king_square == Square::H1)
-        && is_rook_not_on_square(board, Square::H1))
-        || ((king_square == Square::C1 || king_square == Square::B1 || king_square == Square::A1)
-            && is_rook_not_on_square(board, Square::A1)
-            && is_rook_not_on_square(board, Square::B1))
-    {
-        return CASTLED_BONUS;
+// the pawns sheltering the king, and the open files leading to it, for the middlegame
+fn evaluate_king_safety(board: &Board, weights: &EvalWeights, color: Color) -> i32 {
+    let king_square = board.king_square(color);
+    let pawns = board.pieces(Piece::Pawn);
+    let own_pawns = pawns & board.color_combined(color);
+    let king_file = king_square.get_file();
+    let nearby_files = get_file(king_file) | get_adjacent_files(king_file);
+
+    let mut safety = 0;
+    let mut shield_square = king_square.forward(color);
+    for bonus in weights.pawn_shield_bonus {
+        let Some(square) = shield_square else {
+            break;
+        };
+        let shield = own_pawns & nearby_files & get_rank(square.get_rank());
+        safety += shield.popcnt() as i32 * bonus;
+        shield_square = square.forward(color);
     }
-    // if black is castled
-    else if ((king_square == Square::G8 || king_square == Square::H8)
-        && is_rook_not_on_square(board, Square::H8))
-        || ((king_square == Square::C8 || king_square == Square::B8 || king_square == Square::A8)
-            && is_rook_not_on_square(board, Square::A8)
-            && is_rook_not_on_square(board, Square::B8))
-    {
-        return -CASTLED_BONUS;
+
+    for file_index in king_file.to_index().saturating_sub(1)..=(king_file.to_index() + 1).min(7) {
+        let file = get_file(File::from_index(file_index));
+        if own_pawns & file == EMPTY {
+            safety -= weights.king_semi_open_file_penalty;
+            if pawns & file == EMPTY {
+                safety -= weights.king_open_file_penalty;
+            }
+        }
     }
-    return 0;
+    safety
 }
 
-fn can_castle(board: &Board, color: Color, move_ply: u32) -> i16 {
-    let color_multiplier = if color == Color::White { 1 } else { -1 };
-    if board.castle_rights(color) == CastleRights::NoRights && move_ply < ENDGAME_PLY {
-        -200 * color_multiplier
+fn can_castle(board: &Board, weights: &EvalWeights, color: Color) -> i32 {
+    // losing the right to castle only hurts if the king didn't castle first
+    if board.castle_rights(color) == CastleRights::NoRights && !is_castled(board, color) {
+        -weights.no_castle_rights_penalty
     } else {
         0
     }
 }
 
-fn piece_positions(board: &Board, color: Color, move_ply: u32) -> i16 {
-    if move_ply >= MIDGAME_PLY && move_ply < ENDGAME_PLY {
-        return 0;
+// the middlegame and endgame piece square table scores of the pieces of the given color
+fn piece_positions(board: &Board, weights: &EvalWeights, color: Color) -> (i32, i32) {
+    let mut midgame = 0;
+    let mut endgame = 0;
+    for piece in PIECES {
+        let bit_board = board.pieces(*piece) & board.color_combined(color);
+        for square in bit_board {
+            // the tables are from white's point of view, so black looks at them upside down
+            let index = if color == Color::White {
+                square.to_index()
+            } else {
+                square.to_index() ^ 56
+            };
+            midgame += weights.midgame_piece_positions[piece.to_index()][index];
+            endgame += weights.endgame_piece_positions[piece.to_index()][index];
+        }
     }
-    let mut piece_position_evaluation = 0;
-    let piece_positions = if color == Color::White {
-        WHITE_PIECE_POSITIONS
-    } else {
-        BLACK_PIECE_POSITIONS
+    (midgame, endgame)
+}
+
+// the middlegame and endgame scores for how active the pieces of the given color are: how many squares they
+// can go to, how hard they are hitting the enemy king and whether they are on good squares
+fn piece_activity(board: &Board, weights: &EvalWeights, color: Color) -> (i32, i32) {
+    let own_pieces = board.color_combined(color);
+    let pawns = board.pieces(Piece::Pawn);
+    let own_pawns = pawns & own_pieces;
+    let enemy_pawns = pawns & board.color_combined(!color);
+    let blockers = *board.combined();
+    let mut midgame = 0;
+    let mut endgame = 0;
+    let mut add = |(midgame_term, endgame_term): (i32, i32), multiplier: i32| {
+        midgame += midgame_term * multiplier;
+        endgame += endgame_term * multiplier;
     };
 
-    for piece in PIECES {
-        let endgame = move_ply >= ENDGAME_PLY;
-        let piece_position_index = if endgame && *piece == Piece::Pawn {
-            ENDGAME_INDEX_START
-        } else if endgame && *piece == Piece::King {
-            ENDGAME_INDEX_START + 1
-        } else if endgame {
-            continue; // FOR THE MOMENT, DONT RELY ON PIECE POSITIONS FOR ENDGAME IF NOT A PAWN OR KING
-        } else {
-            piece.to_index()
-        };
-        let bit_board = board.pieces(*piece);
-        for square in *bit_board {
-            let file = square.get_file().to_index();
-            let rank = square.get_rank().to_index();
-            let index = NUM_COLUMNS * rank + file;
-            piece_position_evaluation += piece_positions[piece_position_index][index];
+    // squares taken by our own pieces or guarded by enemy pawns aren't much use to move to
+    let safe_squares = !*own_pieces & !pawn_attacks(enemy_pawns, !color);
+    let enemy_king_square = board.king_square(!color);
+    let king_zone = get_king_moves(enemy_king_square) | BitBoard::from_square(enemy_king_square);
+    let mut king_attackers = 0;
+    let mut king_attack_units = 0;
+
+    for (index, piece) in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .into_iter()
+        .enumerate()
+    {
+        for square in board.pieces(piece) & own_pieces {
+            let attacks = match piece {
+                Piece::Knight => get_knight_moves(square),
+                Piece::Bishop => get_bishop_moves(square, blockers),
+                Piece::Rook => get_rook_moves(square, blockers),
+                _ => get_bishop_moves(square, blockers) | get_rook_moves(square, blockers),
+            };
+            let mobility = (attacks & safe_squares).popcnt() as i32;
+            add(
+                weights.mobility_bonus[index],
+                mobility - MOBILITY_BASELINE[index],
+            );
+
+            let king_zone_attacks = (attacks & king_zone).popcnt() as i32;
+            if king_zone_attacks > 0 {
+                king_attackers += 1;
+                king_attack_units += weights.king_attack_weight[index] * king_zone_attacks;
+            }
+        }
+    }
+    // a single piece can't do much against the king on its own
+    if king_attackers >= 2 {
+        let king_attack = (king_attack_units * king_attack_units / KING_ATTACK_DIVISOR)
+            .min(MAX_KING_ATTACK_PENALTY);
+        add((king_attack, 0), 1);
+    }
+
+    if (board.pieces(Piece::Bishop) & own_pieces).popcnt() >= 2 {
+        add(weights.bishop_pair_bonus, 1);
+    }
+
+    let own_pawn_attacks = pawn_attacks(own_pawns, color);
+    for square in board.pieces(Piece::Knight) & own_pieces {
+        let enemy_pawns_ahead = enemy_pawns
+            & get_adjacent_files(square.get_file())
+            & ranks_ahead(square.get_rank(), color);
+        if (3..=5).contains(&relative_rank(square, color))
+            && own_pawn_attacks & BitBoard::from_square(square) != EMPTY
+            && enemy_pawns_ahead == EMPTY
+        {
+            add(weights.knight_outpost_bonus, 1);
+        }
+    }
+
+    for square in board.pieces(Piece::Rook) & own_pieces {
+        let file = get_file(square.get_file());
+        if pawns & file == EMPTY {
+            add(weights.rook_open_file_bonus, 1);
+        } else if own_pawns & file == EMPTY {
+            add(weights.rook_semi_open_file_bonus, 1);
+        }
+        // the seventh rank is only worth having while there are pawns to eat there or a king to cut off
+        let rank = get_rank(square.get_rank());
+        if relative_rank(square, color) == 6
+            && (enemy_pawns & rank != EMPTY || relative_rank(enemy_king_square, color) == 7)
+        {
+            add(weights.rook_on_seventh_bonus, 1);
         }
     }
-    let color_multiplier = if color == Color::White { 1 } else { -1 };
-    piece_position_evaluation * color_multiplier
+
+    (midgame, endgame)
 }
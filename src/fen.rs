@@ -0,0 +1,61 @@
+use std::str::FromStr;
+
+use chess::{Board, BoardBuilder, Color, Error, Piece, ALL_COLORS, ALL_SQUARES};
+
+// the chess crate ignores the move counters of a FEN and always writes these
+const DEFAULT_COUNTERS: &str = " 0 1";
+
+/// Reads a FEN into a board. Unlike `Board::from_str`, a FEN without exactly one king of each color is an
+/// error here rather than a panic further down the line.
+pub fn board_from_fen(fen: &str) -> Result<Board, Error> {
+    let builder = BoardBuilder::from_str(fen)?;
+    for color in ALL_COLORS {
+        let kings = ALL_SQUARES
+            .iter()
+            .filter(|&&square| builder[square] == Some((Piece::King, color)))
+            .count();
+        if kings != 1 {
+            return Err(Error::InvalidBoard);
+        }
+    }
+    Board::try_from(builder)
+}
+
+/// The fullmove number of a FEN, which the chess crate ignores. It is 1 when the FEN leaves it out.
+pub fn fullmove_number(fen: &str) -> u32 {
+    fen.split_whitespace()
+        .nth(5)
+        .and_then(|fullmove| fullmove.parse::<u32>().ok())
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// The halfmove clock of a FEN, which the chess crate ignores. It is 0 when the FEN leaves it out.
+pub fn halfmove_clock(fen: &str) -> u32 {
+    fen.split_whitespace()
+        .nth(4)
+        .and_then(|halfmove| halfmove.parse::<u32>().ok())
+        .unwrap_or(0)
+}
+
+/// How many plies have been played before the given move.
+pub fn move_ply_from_fullmove(fullmove_number: u32, side_to_move: Color) -> u32 {
+    (fullmove_number.max(1) - 1) * 2 + if side_to_move == Color::Black { 1 } else { 0 }
+}
+
+/// The fullmove number of a position `move_ply` plies into the game.
+pub fn fullmove_from_move_ply(move_ply: u32) -> u32 {
+    move_ply / 2 + 1
+}
+
+/// The FEN of the board with the given move counters instead of the `0 1` the chess crate writes.
+pub fn to_fen(board: &Board, halfmove_clock: u32, fullmove_number: u32) -> String {
+    let fen = board.to_string();
+    let placement = fen.strip_suffix(DEFAULT_COUNTERS).unwrap_or(&fen);
+    format!(
+        "{} {} {}",
+        placement,
+        halfmove_clock,
+        fullmove_number.max(1)
+    )
+}
@@ -0,0 +1,72 @@
+use chess::{Board, ChessMove, Piece};
+use serde::{Deserialize, Serialize};
+
+/// A capture or a pawn move can never be undone, so no position from before one can come up again.
+pub fn is_irreversible(board: &Board, chess_move: ChessMove) -> bool {
+    board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+        || board.piece_on(chess_move.get_dest()).is_some()
+}
+
+/// The positions played before the one being searched, which the search needs to see repetitions and the
+/// fifty move rule coming. `Board` only knows about the current position.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameHistory {
+    // the hash of every position since the last capture or pawn move, oldest first, leaving out the current one
+    hashes: Vec<u64>,
+    // plies since the last capture or pawn move, which can go back further than `hashes` when the game
+    // started from a FEN
+    halfmove_clock: u32,
+}
+
+impl GameHistory {
+    /// The history of a game that starts from a position with this halfmove clock, the fifth field of a FEN.
+    pub fn new(halfmove_clock: u32) -> GameHistory {
+        GameHistory {
+            hashes: Vec::new(),
+            halfmove_clock,
+        }
+    }
+
+    /// The history after playing `moves` from `start_position`.
+    pub fn from_moves(
+        start_position: &Board,
+        halfmove_clock: u32,
+        moves: &[ChessMove],
+    ) -> GameHistory {
+        let mut history = GameHistory::new(halfmove_clock);
+        let mut board = *start_position;
+        for chess_move in moves {
+            history.push(&board, *chess_move);
+            board = board.make_move_new(*chess_move);
+        }
+        history
+    }
+
+    /// Records that `chess_move` was played from `board`.
+    pub fn push(&mut self, board: &Board, chess_move: ChessMove) {
+        if is_irreversible(board, chess_move) {
+            self.hashes.clear();
+            self.halfmove_clock = 0;
+        } else {
+            self.hashes.push(board.get_hash());
+            self.halfmove_clock += 1;
+        }
+    }
+
+    pub fn hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// How many times `board`, the current position, has come up before in the game.
+    pub fn repetitions(&self, board: &Board) -> usize {
+        let hash = board.get_hash();
+        self.hashes
+            .iter()
+            .filter(|earlier| **earlier == hash)
+            .count()
+    }
+}
@@ -0,0 +1,20 @@
+pub mod constants;
+pub mod epd;
+pub mod eval_weights;
+pub mod evaluation;
+pub mod fen;
+pub mod game_history;
+pub mod move_orderer;
+pub mod opening_book;
+mod pawn_structure;
+pub mod perft;
+pub mod pgn;
+pub mod polyglot;
+mod polyglot_random;
+pub mod san;
+pub mod search;
+pub mod search_limits;
+pub mod transposition_table;
+pub mod uci;
+pub mod wasm;
+pub mod weights;
@@ -2,6 +2,9 @@ use chess::{BitBoard, Board, ChessMove, Color, Square, NUM_SQUARES};
 
 use crate::{constants::MAX_KILLER_MOVE_PLY, evaluation::get_count_of_piece};
 
+// the transposition table's move was the best one last time, so it goes ahead of everything else
+const HASH_MOVE_BONUS: i32 = 10_000;
+
 const CHECK_BONUS: i32 = 250;
 
 const PROMOTION_BONUS: i32 = 800;
@@ -36,10 +39,16 @@ impl KillerMoveEntry {
 }
 
 pub struct MoveOrderer {
-    history: [[[u8; 2]; NUM_SQUARES]; NUM_SQUARES],
+    history: [[[u32; 2]; NUM_SQUARES]; NUM_SQUARES],
     killer_moves: [KillerMoveEntry; MAX_KILLER_MOVE_PLY],
 }
 
+impl Default for MoveOrderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MoveOrderer {
     pub fn new() -> MoveOrderer {
         MoveOrderer {
@@ -52,17 +61,25 @@ impl MoveOrderer {
         moves: Vec<ChessMove>,
         board: &Board,
         ply_searched: u8,
+        hash_move: Option<ChessMove>,
     ) -> Vec<ChessMove> {
         let mut scored_moves: Vec<(ChessMove, i32)> = moves
             .iter()
-            .map(|&m| (m, self.score(m, board, ply_searched)))
+            .map(|&m| {
+                let hash_bonus = if hash_move == Some(m) {
+                    HASH_MOVE_BONUS
+                } else {
+                    0
+                };
+                (m, self.score(m, board, ply_searched) + hash_bonus)
+            })
             .collect();
 
         // sort based on the precomputed scores
         scored_moves.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
         // extract the sorted moves
-        return scored_moves.into_iter().map(|(m, _)| m).collect();
+        scored_moves.into_iter().map(|(m, _)| m).collect()
     }
 
     fn score(&self, chess_move: ChessMove, board: &Board, ply_searched: u8) -> i32 {
@@ -145,7 +162,7 @@ impl MoveOrderer {
             score -= 25;
         }
 
-        return score;
+        score
     }
 
     pub fn add_killer_move(&mut self, killer_move: ChessMove, ply_searched: u8) {
@@ -159,7 +176,7 @@ impl MoveOrderer {
         }
     }
 
-    pub fn add_history(&mut self, chess_move: ChessMove, maximizing_player: bool, score: u8) {
+    pub fn add_history(&mut self, chess_move: ChessMove, maximizing_player: bool, score: u32) {
         let player_dim: usize = if maximizing_player { 0 } else { 1 };
         self.history[chess_move.get_source().to_index()][chess_move.get_dest().to_index()]
             [player_dim] = score;
@@ -1,75 +1,152 @@
 use rand::prelude::*;
 
-use std::str::FromStr;
+use std::{collections::HashMap, error::Error, fmt, str::FromStr, sync::OnceLock};
 
-use chess::{ChessMove, Square};
+use chess::{Board, ChessMove};
 use rand_distr::WeightedIndex;
 
-struct WeightedChessMove {
-    chess_move: ChessMove,
-    weight: u32,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeightedChessMove {
+    pub chess_move: ChessMove,
+    pub weight: u32,
 }
 
 impl WeightedChessMove {
-    fn new(chess_move: ChessMove, weight: u32) -> Self {
+    pub fn new(chess_move: ChessMove, weight: u32) -> Self {
         Self { chess_move, weight }
     }
 }
 
-fn get_weighted_move(moves: &Vec<WeightedChessMove>) -> Option<ChessMove> {
-    if moves.len() == 0 {
+/// Why an opening book couldn't be loaded. `line` is 1-based.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl BookError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for BookError {}
+
+/// A collection of opening moves, whatever format it is stored in.
+pub trait OpeningBook {
+    /// Every book move for the position, along with how often it should be played.
+    fn moves(&self, board: &Board) -> Vec<WeightedChessMove>;
+
+    /// Picks one of the legal book moves at random, favoring moves with a higher weight.
+    fn choose_move(&self, board: &Board) -> Option<ChessMove> {
+        let moves: Vec<WeightedChessMove> = self
+            .moves(board)
+            .into_iter()
+            .filter(|move_entry| board.legal(move_entry.chess_move))
+            .collect();
+        get_weighted_move(&moves)
+    }
+}
+
+fn get_weighted_move(moves: &[WeightedChessMove]) -> Option<ChessMove> {
+    if moves.is_empty() {
         return None;
     }
     let mut rng = thread_rng();
-    let dist = WeightedIndex::new(moves.iter().map(|move_entry| move_entry.weight)).unwrap();
+    // this fails if every weight is 0, in which case none of the moves are worth playing
+    let dist = WeightedIndex::new(moves.iter().map(|move_entry| move_entry.weight)).ok()?;
     Some(moves[dist.sample(&mut rng)].chess_move)
 }
 
-fn parse_moves(lines: &mut std::str::Lines<'_>) -> Vec<WeightedChessMove> {
-    let mut moves = Vec::new();
+/// An opening book in the Book.txt format: a `pos <hash>` line, where the hash is
+/// `Board::get_hash()` of the position, followed by one `<uci move> <weight>` line per book move.
+pub struct TextBook {
+    positions: HashMap<u64, Vec<WeightedChessMove>>,
+}
 
-    while let Some(line) = lines.next() {
-        let parts: Vec<&str> = line.splitn(2, ' ').collect();
-        if parts.len() != 2 {
-            break;
-        }
+impl TextBook {
+    pub fn parse(text: &str) -> Result<TextBook, BookError> {
+        let mut positions: HashMap<u64, Vec<WeightedChessMove>> = HashMap::new();
+        let mut current_moves: Option<&mut Vec<WeightedChessMove>> = None;
 
-        if parts[0] != "pos" {
-            let pairs: Vec<String> = parts[0]
-                .chars()
-                .collect::<Vec<char>>()
-                .chunks(2)
-                .map(|chunk| chunk.iter().collect())
-                .collect();
-            let source_square = Square::from_str(&pairs[0]).unwrap();
-            let target_square = Square::from_str(&pairs[1]).unwrap();
-            let chess_move = ChessMove::new(source_square, target_square, None);
-            let weight = parts[1].parse::<u32>().unwrap();
-            moves.push(WeightedChessMove::new(chess_move, weight));
-        } else {
-            break;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((first, second)) = line.split_once(' ') else {
+                return Err(BookError::new(
+                    line_number,
+                    format!("expected two fields but found `{}`", line),
+                ));
+            };
+            let second = second.trim();
+
+            if first == "pos" {
+                let hash = second.parse::<u64>().map_err(|_| {
+                    BookError::new(line_number, format!("invalid position hash `{}`", second))
+                })?;
+                current_moves = Some(positions.entry(hash).or_default());
+            } else {
+                let Some(moves) = current_moves.as_mut() else {
+                    return Err(BookError::new(
+                        line_number,
+                        "found a move before any `pos` line",
+                    ));
+                };
+                let chess_move = ChessMove::from_str(first).map_err(|_| {
+                    BookError::new(line_number, format!("invalid move `{}`", first))
+                })?;
+                let weight = second.parse::<u32>().map_err(|_| {
+                    BookError::new(line_number, format!("invalid weight `{}`", second))
+                })?;
+                moves.push(WeightedChessMove::new(chess_move, weight));
+            }
         }
+
+        Ok(TextBook { positions })
     }
 
-    moves
-}
+    /// Every book move for the position with the given `Board::get_hash()`.
+    pub fn moves_for_hash(&self, hash: u64) -> &[WeightedChessMove] {
+        self.positions
+            .get(&hash)
+            .map_or(&[], |moves| moves.as_slice())
+    }
 
-pub fn opening_book_move(target: u64) -> Option<ChessMove> {
-    let file = std::include_str!("../Book.txt");
-    let mut lines = file.lines();
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
 
-    while let Some(line) = lines.next() {
-        let parts: Vec<&str> = line.splitn(2, ' ').collect();
-        if parts.len() != 2 {
-            continue;
-        }
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
 
-        if parts[0] == "pos" {
-            let current_target = parts[1].parse::<u64>().unwrap();
-            if current_target == target {
-                return get_weighted_move(&parse_moves(&mut lines));
-            }
-        }
+/// The book that ships with the engine. It is parsed the first time it is needed.
+pub fn default_book() -> Result<&'static TextBook, &'static BookError> {
+    static BOOK: OnceLock<Result<TextBook, BookError>> = OnceLock::new();
+    BOOK.get_or_init(|| TextBook::parse(std::include_str!("../Book.txt")))
+        .as_ref()
+}
+
+impl OpeningBook for TextBook {
+    fn moves(&self, board: &Board) -> Vec<WeightedChessMove> {
+        self.moves_for_hash(board.get_hash()).to_vec()
     }
-    None
+}
+
+pub fn opening_book_move(board: &Board) -> Option<ChessMove> {
+    default_book().ok()?.choose_move(board)
 }
@@ -0,0 +1,192 @@
+use chess::{
+    get_adjacent_files, get_file, get_pawn_attacks, get_rank, BitBoard, Board, CacheTable, Color,
+    File, Piece, Rank, Square, EMPTY,
+};
+
+use crate::{eval_weights::EvalWeights, polyglot_random::POLYGLOT_RANDOM};
+
+// pawn structures repeat a lot more than positions do, so this can be much smaller than the transposition table
+const PAWN_TABLE_SIZE: usize = 8192;
+
+/// The parts of the evaluation that only depend on where the pawns are, from white's point of view.
+#[derive(Copy, Clone, Default, PartialEq, PartialOrd)]
+pub(crate) struct PawnStructure {
+    pub midgame: i32,
+    pub endgame: i32,
+    /// the passed pawns of both colors. how much they are worth depends on the pieces in their way,
+    /// so they are scored by `passed_pawns` rather than stored with the rest
+    pub passed: BitBoard,
+}
+
+/// Remembers the pawn structures that were already evaluated, keyed on `pawn_key`. Every entry was worked out
+/// with the same weights, so a table shouldn't be shared between searches with different ones.
+pub(crate) struct PawnHashTable {
+    table: CacheTable<PawnStructure>,
+}
+
+impl PawnHashTable {
+    pub fn new() -> PawnHashTable {
+        PawnHashTable {
+            // a board without pawns has a key of 0, which is also what the empty slots hold. that's fine
+            // since the default entry is exactly what evaluating no pawns gives
+            table: CacheTable::new(PAWN_TABLE_SIZE, PawnStructure::default()),
+        }
+    }
+
+    pub fn get_or_evaluate(&mut self, board: &Board, weights: &EvalWeights) -> PawnStructure {
+        let key = pawn_key(board);
+        if let Some(pawn_structure) = self.table.get(key) {
+            return pawn_structure;
+        }
+        let pawn_structure = evaluate_pawn_structure(board, weights);
+        self.table.add(key, pawn_structure);
+        pawn_structure
+    }
+}
+
+/// A zobrist key of just the pawns. `Board::get_pawn_hash()` isn't implemented by the chess crate, so
+/// this uses the pawn entries of the Polyglot random numbers instead.
+pub(crate) fn pawn_key(board: &Board) -> u64 {
+    let mut key = 0;
+    for color in [Color::White, Color::Black] {
+        // black pawns come first, then white pawns
+        let kind = if color == Color::White { 1 } else { 0 };
+        for square in board.pieces(Piece::Pawn) & board.color_combined(color) {
+            key ^= POLYGLOT_RANDOM[64 * kind + square.to_index()];
+        }
+    }
+    key
+}
+
+pub(crate) fn evaluate_pawn_structure(board: &Board, weights: &EvalWeights) -> PawnStructure {
+    let mut pawn_structure = PawnStructure::default();
+    for color in [Color::White, Color::Black] {
+        let color_multiplier = if color == Color::White { 1 } else { -1 };
+        let (midgame, endgame, passed) = pawn_terms(board, weights, color);
+        pawn_structure.midgame += midgame * color_multiplier;
+        pawn_structure.endgame += endgame * color_multiplier;
+        pawn_structure.passed |= passed;
+    }
+    pawn_structure
+}
+
+/// Scores the passed pawns found by `evaluate_pawn_structure`, from white's point of view. A passed pawn
+/// is worth more the further it has come, and less while something stands in its way.
+pub(crate) fn passed_pawns(board: &Board, weights: &EvalWeights, passed: BitBoard) -> (i32, i32) {
+    let mut midgame = 0;
+    let mut endgame = 0;
+    for square in passed {
+        let Some(color) = board.color_on(square) else {
+            continue;
+        };
+        let color_multiplier = if color == Color::White { 1 } else { -1 };
+        let (mut pawn_midgame, mut pawn_endgame) =
+            weights.passed_pawn_bonus[relative_rank(square, color)];
+        let path = get_file(square.get_file()) & ranks_ahead(square.get_rank(), color);
+        if path & board.combined() != EMPTY {
+            pawn_midgame /= 2;
+            pawn_endgame /= 2;
+        }
+        midgame += pawn_midgame * color_multiplier;
+        endgame += pawn_endgame * color_multiplier;
+    }
+    (midgame, endgame)
+}
+
+// the middlegame and endgame pawn structure scores of the given color, along with its passed pawns
+fn pawn_terms(board: &Board, weights: &EvalWeights, color: Color) -> (i32, i32, BitBoard) {
+    let pawns = board.pieces(Piece::Pawn);
+    let own_pawns = pawns & board.color_combined(color);
+    let enemy_pawns = pawns & board.color_combined(!color);
+    let mut midgame = 0;
+    let mut endgame = 0;
+    let mut add = |(midgame_term, endgame_term): (i32, i32), multiplier: i32| {
+        midgame += midgame_term * multiplier;
+        endgame += endgame_term * multiplier;
+    };
+    let mut passed = EMPTY;
+
+    for square in own_pawns {
+        let file = get_file(square.get_file());
+        let adjacent_files = get_adjacent_files(square.get_file());
+        let ahead = ranks_ahead(square.get_rank(), color);
+        let rank = relative_rank(square, color);
+
+        if enemy_pawns & (file | adjacent_files) & ahead == EMPTY
+            && own_pawns & file & ahead == EMPTY
+        {
+            passed |= BitBoard::from_square(square);
+        }
+
+        let isolated = own_pawns & adjacent_files == EMPTY;
+        if isolated {
+            add(weights.isolated_pawn_penalty, -1);
+        }
+
+        // a pawn defended by another pawn, or standing next to one
+        if get_pawn_attacks(square, !color, own_pawns) != EMPTY {
+            add(weights.supported_pawn_bonus[rank], 1);
+        }
+        if own_pawns & adjacent_files & get_rank(square.get_rank()) != EMPTY {
+            add(weights.phalanx_pawn_bonus[rank], 1);
+        }
+
+        // a pawn that has been left behind by the pawns next to it, so none of them can ever defend it,
+        // and which can't catch up because an enemy pawn guards the square in front of it
+        let can_be_supported = own_pawns & adjacent_files & !ahead != EMPTY;
+        let stop_is_guarded = square
+            .forward(color)
+            .is_some_and(|stop| get_pawn_attacks(stop, color, enemy_pawns) != EMPTY);
+        if !isolated && !can_be_supported && stop_is_guarded {
+            add(weights.backward_pawn_penalty, -1);
+        }
+    }
+
+    let mut occupied_files = 0u8;
+    for file_index in 0..8 {
+        let pawns_on_file = (own_pawns & get_file(File::from_index(file_index))).popcnt() as i32;
+        if pawns_on_file > 1 {
+            add(weights.doubled_pawn_penalty, -(pawns_on_file - 1));
+        }
+        if pawns_on_file > 0 {
+            occupied_files |= 1 << file_index;
+        }
+    }
+    // an island starts at every occupied file that doesn't have an occupied file to its left
+    let islands = (occupied_files & !(occupied_files << 1)).count_ones() as i32;
+    if islands > 1 {
+        add(weights.pawn_island_penalty, -(islands - 1));
+    }
+
+    (midgame, endgame, passed)
+}
+
+/// How far the square is from the given color's side of the board, from 0 on its back rank to 7.
+pub(crate) fn relative_rank(square: Square, color: Color) -> usize {
+    if color == Color::White {
+        square.get_rank().to_index()
+    } else {
+        7 - square.get_rank().to_index()
+    }
+}
+
+/// Every square on a rank in front of the given rank, as seen by the given color.
+pub(crate) fn ranks_ahead(rank: Rank, color: Color) -> BitBoard {
+    let rank_index = rank.to_index() as u32;
+    if color == Color::White {
+        BitBoard(u64::MAX.checked_shl(8 * (rank_index + 1)).unwrap_or(0))
+    } else {
+        BitBoard((1u64 << (8 * rank_index)) - 1)
+    }
+}
+
+/// Every square attacked by the given pawns.
+pub(crate) fn pawn_attacks(pawns: BitBoard, color: Color) -> BitBoard {
+    let not_a_file = !get_file(File::A);
+    let not_h_file = !get_file(File::H);
+    if color == Color::White {
+        BitBoard(((pawns & not_a_file).0 << 7) | ((pawns & not_h_file).0 << 9))
+    } else {
+        BitBoard(((pawns & not_a_file).0 >> 9) | ((pawns & not_h_file).0 >> 7))
+    }
+}
@@ -0,0 +1,29 @@
+use chess::{Board, ChessMove, MoveGen};
+
+/// The number of leaf nodes in the tree of legal moves `depth` plies deep. Comparing this against known
+/// counts is the standard way of checking that move generation is right.
+pub fn perft(board: &Board, depth: u8) -> u64 {
+    match depth {
+        0 => 1,
+        // the moves themselves are the leaves, so there is no need to make them
+        1 => MoveGen::new_legal(board).len() as u64,
+        _ => MoveGen::new_legal(board)
+            .map(|chess_move| perft(&board.make_move_new(chess_move), depth - 1))
+            .sum(),
+    }
+}
+
+/// `perft` split up by the first move, which narrows down where a wrong count comes from.
+pub fn divide(board: &Board, depth: u8) -> Vec<(ChessMove, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    MoveGen::new_legal(board)
+        .map(|chess_move| {
+            (
+                chess_move,
+                perft(&board.make_move_new(chess_move), depth - 1),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,519 @@
+use std::{error::Error, fmt, iter::Peekable, str::Chars, str::FromStr};
+
+use chess::{Action, Board, ChessMove, Color, Game, GameResult};
+
+use crate::{
+    fen::{fullmove_number, halfmove_clock, to_fen},
+    san::{move_to_san, san_to_move},
+    search::Score,
+};
+
+// the tags every PGN file is supposed to have, in the order they have to be written
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// export format lines have to be shorter than 80 characters
+const MAX_LINE_LENGTH: usize = 79;
+
+// what the move suffix annotations mean as numeric annotation glyphs
+const SUFFIX_ANNOTATIONS: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+/// Why a PGN couldn't be read. `line` is 1-based.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl PgnError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for PgnError {}
+
+/// A move of the main line, along with what was said about it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnMove {
+    pub chess_move: ChessMove,
+    pub comment: Option<String>,
+    /// numeric annotation glyphs, like 1 for a good move
+    pub nags: Vec<u8>,
+}
+
+impl PgnMove {
+    pub fn new(chess_move: ChessMove) -> Self {
+        Self {
+            chess_move,
+            comment: None,
+            nags: Vec::new(),
+        }
+    }
+}
+
+/// A single game in Portable Game Notation. Only the main line is kept, variations are skipped
+/// when reading.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pgn {
+    /// every tag pair in the order they are written
+    pub tags: Vec<(String, String)>,
+    pub start_position: Board,
+    pub moves: Vec<PgnMove>,
+}
+
+impl Pgn {
+    /// The moves of `game` along with the Seven Tag Roster, where the tags we don't know are `?`.
+    /// `halfmove_clock` and `first_move_number` are the move counters of the start position.
+    pub fn from_game(
+        game: &Game,
+        start_position: Board,
+        halfmove_clock: u32,
+        first_move_number: u32,
+    ) -> Pgn {
+        let mut pgn = Pgn {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            start_position,
+            moves: game
+                .actions()
+                .iter()
+                .filter_map(|action| match action {
+                    Action::MakeMove(chess_move) => Some(PgnMove::new(*chess_move)),
+                    _ => None,
+                })
+                .collect(),
+        };
+        pgn.set_tag("Result", result_token(game.result()));
+        if start_position != Board::default() || halfmove_clock != 0 || first_move_number != 1 {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag(
+                "FEN",
+                to_fen(&start_position, halfmove_clock, first_move_number),
+            );
+        }
+        pgn
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Changes the value of a tag, adding it after the others if it isn't there yet.
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, tag_value)) => *tag_value = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// Replays the moves. A result that wasn't reached on the board, like a resignation, isn't
+    /// replayed, so the game can be continued from where it stopped.
+    pub fn to_game(&self) -> Game {
+        let mut game = Game::new_with_board(self.start_position);
+        for pgn_move in &self.moves {
+            game.make_move(pgn_move.chess_move);
+        }
+        game
+    }
+
+    /// Reads the first game of a PGN file. Comments and annotation glyphs are kept for the main
+    /// line, variations are skipped.
+    pub fn parse(text: &str) -> Result<Pgn, PgnError> {
+        let mut tokenizer = Tokenizer::new(text);
+        let mut tags: Vec<(String, String)> = Vec::new();
+        let mut board: Option<Board> = None;
+        let mut start_position = Board::default();
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut variation_depth = 0;
+        let mut result = None;
+
+        while let Some((line, token)) = tokenizer.next_token()? {
+            if let Token::Tag(name, value) = token {
+                if board.is_some() {
+                    // this is the start of the next game
+                    break;
+                }
+                tags.push((name, value));
+                continue;
+            }
+            let current_board = match board.as_mut() {
+                Some(current_board) => current_board,
+                None => {
+                    start_position = start_position_from_tags(&tags, line)?;
+                    board.insert(start_position)
+                }
+            };
+            match token {
+                Token::Tag(..) => unreachable!(),
+                Token::VariationStart => variation_depth += 1,
+                Token::VariationEnd => {
+                    if variation_depth == 0 {
+                        return Err(PgnError::new(line, "found `)` outside of a variation"));
+                    }
+                    variation_depth -= 1;
+                }
+                _ if variation_depth > 0 => {}
+                Token::Comment(comment) => {
+                    // a comment before the first move is about the whole game, which we don't keep
+                    if let Some(last_move) = moves.last_mut() {
+                        last_move.comment = Some(match last_move.comment.take() {
+                            Some(earlier) => format!("{} {}", earlier, comment),
+                            None => comment,
+                        });
+                    }
+                }
+                Token::Nag(nag) => {
+                    // `Qb8+!! $3` gives the same glyph twice, once as a suffix and once as a number
+                    if let Some(last_move) = moves
+                        .last_mut()
+                        .filter(|last_move| !last_move.nags.contains(&nag))
+                    {
+                        last_move.nags.push(nag);
+                    }
+                }
+                Token::Result(token) => {
+                    result = Some(token);
+                    break;
+                }
+                Token::Move(san, suffix_nag) => {
+                    let chess_move = san_to_move(current_board, &san).ok_or_else(|| {
+                        PgnError::new(line, format!("`{}` is not a legal move", san))
+                    })?;
+                    *current_board = current_board.make_move_new(chess_move);
+                    let mut pgn_move = PgnMove::new(chess_move);
+                    pgn_move.nags.extend(suffix_nag);
+                    moves.push(pgn_move);
+                }
+            }
+        }
+
+        if board.is_none() {
+            if tags.is_empty() {
+                return Err(PgnError::new(tokenizer.line, "there is no game"));
+            }
+            start_position = start_position_from_tags(&tags, tokenizer.line)?;
+        }
+        let mut pgn = Pgn {
+            tags,
+            start_position,
+            moves,
+        };
+        if let Some(result) = result {
+            pgn.set_tag("Result", result);
+        }
+        Ok(pgn)
+    }
+
+    /// The fullmove number of the start position, which is only different from 1 when the game
+    /// starts from a FEN.
+    pub fn first_move_number(&self) -> u32 {
+        self.tag("FEN").map_or(1, fullmove_number)
+    }
+
+    /// The halfmove clock of the start position, which is only different from 0 when the game
+    /// starts from a FEN.
+    pub fn start_halfmove_clock(&self) -> u32 {
+        self.tag("FEN").map_or(0, halfmove_clock)
+    }
+}
+
+impl fmt::Display for Pgn {
+    /// Writes the game in the PGN export format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        writeln!(f)?;
+
+        let mut words = Vec::new();
+        let mut board = self.start_position;
+        let mut move_number = self.first_move_number();
+        // black's moves only need a number at the start, or after a comment interrupts the moves
+        let mut needs_number = true;
+        for pgn_move in &self.moves {
+            let san = move_to_san(&board, pgn_move.chess_move);
+            // keep the number on the same line as its move
+            words.push(if board.side_to_move() == Color::White {
+                format!("{}. {}", move_number, san)
+            } else if needs_number {
+                format!("{}... {}", move_number, san)
+            } else {
+                san
+            });
+            words.extend(pgn_move.nags.iter().map(|nag| format!("${}", nag)));
+            needs_number = false;
+            if let Some(comment) = &pgn_move.comment {
+                words.push(format!("{{{}}}", comment.replace('}', "")));
+                needs_number = true;
+            }
+            if board.side_to_move() == Color::Black {
+                move_number += 1;
+            }
+            board = board.make_move_new(pgn_move.chess_move);
+        }
+        words.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line_length = 0;
+        for word in words {
+            if line_length > 0 && line_length + 1 + word.len() > MAX_LINE_LENGTH {
+                writeln!(f)?;
+                line_length = 0;
+            } else if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{}", word)?;
+            line_length += word.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// The token that ends the movetext of a game with this result.
+pub fn result_token(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::WhiteCheckmates) | Some(GameResult::BlackResigns) => "1-0",
+        Some(GameResult::BlackCheckmates) | Some(GameResult::WhiteResigns) => "0-1",
+        Some(GameResult::Stalemate)
+        | Some(GameResult::DrawAccepted)
+        | Some(GameResult::DrawDeclared) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// An engine evaluation as a move comment, like `+0.35/12` or `-M3/20`. PGN evaluations are from
+/// white's point of view, unlike `Score` which is from the point of view of `side_to_move`.
+pub fn eval_comment(score: Score, side_to_move: Color, depth: u8) -> String {
+    let sign = if side_to_move == Color::White { 1 } else { -1 };
+    let eval = match score {
+        Score::Centipawns(centipawns) => format!("{:+.2}", (sign * centipawns) as f64 / 100.0),
+        Score::Mate(moves) if sign * moves < 0 => format!("-M{}", moves.abs()),
+        Score::Mate(moves) => format!("+M{}", moves.abs()),
+    };
+    format!("{}/{}", eval, depth)
+}
+
+fn start_position_from_tags(tags: &[(String, String)], line: usize) -> Result<Board, PgnError> {
+    match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => {
+            Board::from_str(fen).map_err(|_| PgnError::new(line, format!("invalid FEN `{}`", fen)))
+        }
+        None => Ok(Board::default()),
+    }
+}
+
+enum Token {
+    Tag(String, String),
+    /// a move in SAN, and the glyph of its `!` or `?` suffix if it had one
+    Move(String, Option<u8>),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    // everything up to the given character, which is consumed but not returned
+    fn read_until(&mut self, end: char) -> Option<String> {
+        let mut text = String::new();
+        loop {
+            match self.next_char()? {
+                c if c == end => return Some(text),
+                c => text.push(c),
+            }
+        }
+    }
+
+    // the rest of the line, which might be the last one
+    fn read_line(&mut self) -> String {
+        let mut text = String::new();
+        while let Some(c) = self.next_char() {
+            if c == '\n' {
+                break;
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    // the token along with the line it starts on
+    fn next_token(&mut self) -> Result<Option<(usize, Token)>, PgnError> {
+        loop {
+            let Some(&c) = self.chars.peek() else {
+                return Ok(None);
+            };
+            let line = self.line;
+            match c {
+                c if c.is_whitespace() => {
+                    self.next_char();
+                }
+                // an escaped line, for data that isn't PGN
+                '%' => {
+                    self.read_line();
+                }
+                ';' => {
+                    self.next_char();
+                    let comment = self.read_line().trim().to_string();
+                    return Ok(Some((line, Token::Comment(comment))));
+                }
+                '{' => {
+                    self.next_char();
+                    let comment = self
+                        .read_until('}')
+                        .ok_or_else(|| PgnError::new(line, "comment is never closed"))?;
+                    let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                    return Ok(Some((line, Token::Comment(comment))));
+                }
+                '[' => {
+                    self.next_char();
+                    return self.read_tag(line).map(|token| Some((line, token)));
+                }
+                '(' => {
+                    self.next_char();
+                    return Ok(Some((line, Token::VariationStart)));
+                }
+                ')' => {
+                    self.next_char();
+                    return Ok(Some((line, Token::VariationEnd)));
+                }
+                '*' => {
+                    self.next_char();
+                    return Ok(Some((line, Token::Result("*".to_string()))));
+                }
+                '$' => {
+                    self.next_char();
+                    let number = self.read_symbol();
+                    let nag = number.parse().map_err(|_| {
+                        PgnError::new(line, format!("invalid annotation glyph `${}`", number))
+                    })?;
+                    return Ok(Some((line, Token::Nag(nag))));
+                }
+                _ => {
+                    let symbol = self.read_symbol();
+                    if symbol.is_empty() {
+                        return Err(PgnError::new(line, format!("unexpected `{}`", c)));
+                    }
+                    if let Some(token) = classify_symbol(&symbol) {
+                        return Ok(Some((line, token)));
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_symbol(&mut self) -> String {
+        let mut symbol = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || "_+#=:-/.!?".contains(c)) {
+                break;
+            }
+            symbol.push(c);
+            self.next_char();
+        }
+        symbol
+    }
+
+    fn read_tag(&mut self, line: usize) -> Result<Token, PgnError> {
+        let invalid_tag = || PgnError::new(line, "tags must look like [Name \"value\"]");
+        let name = self
+            .read_until('"')
+            .ok_or_else(invalid_tag)?
+            .trim()
+            .to_string();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(invalid_tag());
+        }
+        let mut value = String::new();
+        loop {
+            match self.next_char().ok_or_else(invalid_tag)? {
+                '\\' => value.push(self.next_char().ok_or_else(invalid_tag)?),
+                '"' => break,
+                c => value.push(c),
+            }
+        }
+        if !self
+            .read_until(']')
+            .ok_or_else(invalid_tag)?
+            .trim()
+            .is_empty()
+        {
+            return Err(invalid_tag());
+        }
+        Ok(Token::Tag(name, value))
+    }
+}
+
+// a symbol is a move number, a move, or the result. move numbers are skipped
+fn classify_symbol(symbol: &str) -> Option<Token> {
+    if ["1-0", "0-1", "1/2-1/2"].contains(&symbol) {
+        return Some(Token::Result(symbol.to_string()));
+    }
+    // the move number can be stuck to the move, like `12...Nf6`
+    let without_number = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+    let san = if without_number.starts_with('.') {
+        without_number.trim_start_matches('.')
+    } else {
+        symbol
+    };
+    if san.is_empty() {
+        return None;
+    }
+    let suffix_start = san.find(['!', '?']).unwrap_or(san.len());
+    let suffix_nag = SUFFIX_ANNOTATIONS
+        .iter()
+        .find(|(suffix, _)| *suffix == &san[suffix_start..])
+        .map(|(_, nag)| *nag);
+    Some(Token::Move(san[..suffix_start].to_string(), suffix_nag))
+}
@@ -0,0 +1,234 @@
+use std::io::{self, Read, Write};
+
+use chess::{Board, ChessMove, Color, File, Piece, Rank, Square};
+
+use crate::{
+    opening_book::{OpeningBook, WeightedChessMove},
+    polyglot_random::POLYGLOT_RANDOM,
+};
+
+const CASTLE_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+// every entry is a 64 bit key, a 16 bit move, a 16 bit weight and a 32 bit learn value, all big endian
+const ENTRY_SIZE: usize = 16;
+
+/// The key of a position in a Polyglot book. This is a standard zobrist hash, unlike `Board::get_hash()`
+/// which is specific to the chess crate.
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key = 0;
+
+    for square in *board.combined() {
+        let piece = board.piece_on(square).unwrap();
+        let color = board.color_on(square).unwrap();
+        // black pawn, white pawn, black knight, white knight, ...
+        let kind = piece.to_index() * 2 + if color == Color::White { 1 } else { 0 };
+        key ^= POLYGLOT_RANDOM[64 * kind + square.to_index()];
+    }
+
+    for (color, offset) in [(Color::White, 0), (Color::Black, 2)] {
+        let castle_rights = board.castle_rights(color);
+        if castle_rights.has_kingside() {
+            key ^= POLYGLOT_RANDOM[CASTLE_OFFSET + offset];
+        }
+        if castle_rights.has_queenside() {
+            key ^= POLYGLOT_RANDOM[CASTLE_OFFSET + offset + 1];
+        }
+    }
+
+    // the chess crate only sets the en passant square when a pawn is next to the pawn that just
+    // double pushed, which is exactly when Polyglot hashes it
+    if let Some(en_passant_square) = board.en_passant() {
+        key ^= POLYGLOT_RANDOM[EN_PASSANT_OFFSET + en_passant_square.get_file().to_index()];
+    }
+
+    if board.side_to_move() == Color::White {
+        key ^= POLYGLOT_RANDOM[TURN_OFFSET];
+    }
+    key
+}
+
+/// Decodes a Polyglot move for the given position. Polyglot writes castling as the king capturing
+/// its own rook, so e1h1 becomes e1g1 like the chess crate expects.
+pub fn decode_move(board: &Board, encoded_move: u16) -> ChessMove {
+    let square_at = |shift: u16| {
+        Square::make_square(
+            Rank::from_index(((encoded_move >> (shift + 3)) & 7) as usize),
+            File::from_index(((encoded_move >> shift) & 7) as usize),
+        )
+    };
+    let dest = square_at(0);
+    let source = square_at(6);
+    let promotion = match (encoded_move >> 12) & 7 {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None,
+    };
+
+    if is_castle(board, source, dest) {
+        let dest_file = if dest.get_file() == File::H {
+            File::G
+        } else {
+            File::C
+        };
+        return ChessMove::new(
+            source,
+            Square::make_square(dest.get_rank(), dest_file),
+            None,
+        );
+    }
+    ChessMove::new(source, dest, promotion)
+}
+
+/// The inverse of `decode_move`.
+pub fn encode_move(board: &Board, chess_move: ChessMove) -> u16 {
+    let source = chess_move.get_source();
+    let mut dest = chess_move.get_dest();
+    let is_king_move = board.piece_on(source) == Some(Piece::King);
+    let file_distance = source.get_file().to_index() as i8 - dest.get_file().to_index() as i8;
+    if is_king_move && file_distance.abs() == 2 {
+        // castling, so point at the rook instead
+        let rook_file = if dest.get_file() == File::G {
+            File::H
+        } else {
+            File::A
+        };
+        dest = Square::make_square(dest.get_rank(), rook_file);
+    }
+    let promotion = match chess_move.get_promotion() {
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(Piece::Queen) => 4,
+        _ => 0,
+    };
+    let encode_square =
+        |square: Square| (square.get_rank().to_index() << 3 | square.get_file().to_index()) as u16;
+    promotion << 12 | encode_square(source) << 6 | encode_square(dest)
+}
+
+fn is_castle(board: &Board, source: Square, dest: Square) -> bool {
+    let Some(color) = board.color_on(source) else {
+        return false;
+    };
+    let back_rank = color.to_my_backrank();
+    let castle_rights = board.castle_rights(color);
+    board.piece_on(source) == Some(Piece::King)
+        && source == Square::make_square(back_rank, File::E)
+        && ((dest == Square::make_square(back_rank, File::H) && castle_rights.has_kingside())
+            || (dest == Square::make_square(back_rank, File::A) && castle_rights.has_queenside()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PolyglotEntry {
+    key: u64,
+    encoded_move: u16,
+    weight: u16,
+    learn: u32,
+}
+
+/// An opening book in the Polyglot .bin format, which most chess software can read and write.
+#[derive(Default)]
+pub struct PolyglotBook {
+    // sorted by key so we can binary search it, as the format requires
+    entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    pub fn new() -> PolyglotBook {
+        PolyglotBook::default()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<PolyglotBook> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "entry {} is truncated, a polyglot book must be a multiple of {} bytes long",
+                    bytes.len() / ENTRY_SIZE + 1,
+                    ENTRY_SIZE
+                ),
+            ));
+        }
+        let mut entries: Vec<PolyglotEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| PolyglotEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                encoded_move: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+        // books are supposed to be sorted already, but don't count on it
+        entries.sort_by_key(|entry| entry.key);
+        Ok(PolyglotBook { entries })
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<PolyglotBook> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        PolyglotBook::from_bytes(&bytes)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writer.write_all(&entry.key.to_be_bytes())?;
+            writer.write_all(&entry.encoded_move.to_be_bytes())?;
+            writer.write_all(&entry.weight.to_be_bytes())?;
+            writer.write_all(&entry.learn.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Adds a move to the book, or replaces its weight if the move is already in the book.
+    pub fn insert(&mut self, board: &Board, chess_move: ChessMove, weight: u16) {
+        let key = polyglot_key(board);
+        let encoded_move = encode_move(board, chess_move);
+        let range = self.entry_range(key);
+        if let Some(entry) = self.entries[range.clone()]
+            .iter_mut()
+            .find(|entry| entry.encoded_move == encoded_move)
+        {
+            entry.weight = weight;
+            return;
+        }
+        self.entries.insert(
+            range.end,
+            PolyglotEntry {
+                key,
+                encoded_move,
+                weight,
+                learn: 0,
+            },
+        );
+    }
+
+    /// The number of moves in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn entry_range(&self, key: u64) -> std::ops::Range<usize> {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        start..end
+    }
+}
+
+impl OpeningBook for PolyglotBook {
+    fn moves(&self, board: &Board) -> Vec<WeightedChessMove> {
+        self.entries[self.entry_range(polyglot_key(board))]
+            .iter()
+            .map(|entry| {
+                WeightedChessMove::new(decode_move(board, entry.encoded_move), entry.weight as u32)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,116 @@
+use chess::{Action, Board, BoardStatus, ChessMove, Color, File, MoveGen, Piece};
+
+/// Writes a legal move in Standard Algebraic Notation, like `Nbd7`, `exd5`, `O-O`, `e8=Q+` or `Qh4#`.
+pub fn move_to_san(board: &Board, chess_move: ChessMove) -> String {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = board
+        .piece_on(source)
+        .expect("a move must start on a square with a piece");
+    let file_distance = source.get_file().to_index() as i8 - dest.get_file().to_index() as i8;
+
+    let mut san = String::new();
+    if piece == Piece::King && file_distance.abs() == 2 {
+        san.push_str(if dest.get_file() == File::G {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else {
+        // a pawn changing files is always a capture, even when the target square is empty (en passant)
+        let is_capture =
+            board.piece_on(dest).is_some() || (piece == Piece::Pawn && file_distance != 0);
+        if piece == Piece::Pawn {
+            if is_capture {
+                san.push_str(&file_name(source.get_file()));
+            }
+        } else {
+            san.push_str(&piece.to_string(Color::White));
+            san.push_str(&disambiguation(board, chess_move, piece));
+        }
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+        if let Some(promotion) = chess_move.get_promotion() {
+            san.push('=');
+            san.push_str(&promotion.to_string(Color::White));
+        }
+    }
+
+    let board_after_move = board.make_move_new(chess_move);
+    if board_after_move.status() == BoardStatus::Checkmate {
+        san.push('#');
+    } else if board_after_move.checkers().popcnt() > 0 {
+        san.push('+');
+    }
+    san
+}
+
+/// Every move made in the game, in SAN, starting from `start_position`. Draw offers and
+/// resignations are skipped since they aren't moves.
+pub fn game_to_san(start_position: &Board, actions: &[Action]) -> Vec<String> {
+    let mut board = *start_position;
+    let mut sans = Vec::new();
+    for action in actions {
+        if let Action::MakeMove(chess_move) = action {
+            sans.push(move_to_san(&board, *chess_move));
+            board = board.make_move_new(*chess_move);
+        }
+    }
+    sans
+}
+
+// the part of the move that tells it apart from moves of the same kind of piece to the same square.
+// the file is preferred, then the rank, and only if neither is enough both are used
+fn disambiguation(board: &Board, chess_move: ChessMove, piece: Piece) -> String {
+    let source = chess_move.get_source();
+    let others: Vec<_> = MoveGen::new_legal(board)
+        .filter(|legal_move| {
+            legal_move.get_dest() == chess_move.get_dest()
+                && legal_move.get_source() != source
+                && board.piece_on(legal_move.get_source()) == Some(piece)
+        })
+        .map(|legal_move| legal_move.get_source())
+        .collect();
+
+    if others.is_empty() {
+        String::new()
+    } else if others
+        .iter()
+        .all(|other| other.get_file() != source.get_file())
+    {
+        file_name(source.get_file())
+    } else if others
+        .iter()
+        .all(|other| other.get_rank() != source.get_rank())
+    {
+        (source.get_rank().to_index() + 1).to_string()
+    } else {
+        source.to_string()
+    }
+}
+
+fn file_name(file: File) -> String {
+    ((b'a' + file.to_index() as u8) as char).to_string()
+}
+
+/// Reads a move in SAN for the given position, or none if it isn't a legal move there. Check and
+/// annotation suffixes are ignored, castling may be written with zeros and the `=` of a promotion
+/// may be left out.
+pub fn san_to_move(board: &Board, san: &str) -> Option<ChessMove> {
+    let wanted = normalize_san(san);
+    if wanted.is_empty() {
+        return None;
+    }
+    MoveGen::new_legal(board)
+        .find(|&legal_move| normalize_san(&move_to_san(board, legal_move)) == wanted)
+}
+
+fn normalize_san(san: &str) -> String {
+    san.trim()
+        .trim_end_matches(['+', '#', '!', '?'])
+        // no square has a 0 in it, so this only touches castling
+        .replace('0', "O")
+        .replace('=', "")
+}
@@ -1,219 +1,912 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use crate::{
-    constants::{CHECKMATE_EVAL, MAX_DEPTH, MAX_EXTENSIONS},
-    evaluation::board_eval,
+    constants::{
+        AI_MOVE_TIME_MS, CHECKMATE_EVAL, DEFAULT_CONTEMPT, DEFAULT_HASH_MB, MAX_EXTENSIONS,
+    },
+    eval_weights::EvalWeights,
+    evaluation::{board_eval_with_pawn_table, get_count_of_piece_with_weights},
+    game_history::{is_irreversible, GameHistory},
     move_orderer::MoveOrderer,
-    transposition_table::{TranspositionTable, Type},
+    pawn_structure::PawnHashTable,
+    search_limits::{Clock, SearchLimits, SystemClock},
+    transposition_table::{is_mate_eval, plies_to_mate, TranspositionTable, Type},
+    weights::WEIGHTS,
 };
-use chess::{Board, BoardStatus, ChessMove, Color, MoveGen, Square};
-use gloo_console::log;
+use chess::{get_rank, BitBoard, Board, BoardStatus, ChessMove, Color, MoveGen, Piece, Square};
+use serde::{Deserialize, Serialize};
 
-fn search(
-    board: &Board,
-    transposition_table: &mut TranspositionTable,
-    move_orderer: &mut MoveOrderer,
-    ply_remaining: u8,
-    ply_searched: u8,
-    num_extensions: u8,
-    mut alpha: i32,
-    mut beta: i32,
-    move_ply: u32,
-) -> (i32, Option<ChessMove>) {
-    let orig_alpha = alpha;
-    let maximizing_player = board.side_to_move() == Color::White;
-    /* base cases for search function */
-    /* 1. we have already seen this position before */
-    if let Some(evaluation_move_pair) =
-        transposition_table.get(board.get_hash(), ply_remaining, ply_searched, alpha, beta)
-    {
-        return evaluation_move_pair;
-    }
-
-    /* 2. Either we have reached 0 depth or our game finished */
-    if ply_remaining <= 0 || board.status() != BoardStatus::Ongoing {
-        // if on 0 depth but game is ongoing, then do quinescent search
-        let evaluation = if board.status() == BoardStatus::Checkmate && maximizing_player {
-            -CHECKMATE_EVAL + ply_searched as i32
-        } else if board.status() == BoardStatus::Checkmate {
-            CHECKMATE_EVAL - ply_searched as i32
-        } else if board.status() == BoardStatus::Stalemate {
-            0
+// how many nodes we search between checks of the stop flag and the clock
+const STOP_CHECK_INTERVAL: u64 = 2048;
+
+// how much a capture is allowed to fall short of alpha in the quiescence search before we prune it,
+// to account for positional gains that come along with the material
+const DELTA_MARGIN: i32 = 200;
+
+// null move pruning needs enough depth left for the reduced search to tell us something
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+
+// how many plies shallower than a normal move the null move is searched, on top of the ply it uses up
+const NULL_MOVE_REDUCTION: u8 = 2;
+
+// reverse futility pruning cuts off positions this close to the leaves that are further above beta than this
+// margin per remaining ply
+const REVERSE_FUTILITY_MAX_DEPTH: u8 = 6;
+const REVERSE_FUTILITY_MARGIN: i32 = 120;
+
+// futility pruning skips quiet moves this close to the leaves when the position is further below alpha than
+// this margin per remaining ply
+const FUTILITY_MAX_DEPTH: u8 = 2;
+const FUTILITY_MARGIN: i32 = 150;
+
+// late move pruning skips the quiet moves after the first few this close to the leaves
+const LATE_MOVE_PRUNING_MAX_DEPTH: u8 = 3;
+
+// late move reductions start with the move at this index, and only with this much depth left
+const LATE_MOVE_REDUCTION_MIN_MOVES: usize = 3;
+const LATE_MOVE_REDUCTION_MIN_DEPTH: u8 = 3;
+
+// aspiration windows start out this far either side of the last iteration's score, and double every time the
+// score falls outside of them
+const ASPIRATION_WINDOW: i32 = 50;
+
+// the first iterations are too shallow for their scores to say much about the next one
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+
+// the deepest ply the principal variation table has room for
+const MAX_PLY: usize = u8::MAX as usize + 1;
+
+/// An evaluation from the point of view of the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Score {
+    Centipawns(i32),
+    /// the number of moves (not plies) until mate. negative when the side to move is getting mated
+    Mate(i32),
+}
+
+impl Score {
+    fn from_eval(eval: i32) -> Score {
+        match plies_to_mate(eval) {
+            // a mate in 1 is 1 ply away, a mate in 2 is 3 plies away and so on
+            Some(plies) if plies > 0 => Score::Mate((plies + 1) / 2),
+            Some(plies) => Score::Mate((plies - 1) / 2),
+            None => Score::Centipawns(eval),
+        }
+    }
+}
+
+/// What the search found after finishing an iteration of iterative deepening.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<ChessMove>,
+    pub score: Score,
+    /// the last depth that was searched completely
+    pub depth: u8,
+    /// the deepest ply reached, including extensions and the quiescence search
+    pub seldepth: u8,
+    pub nodes: u64,
+    /// milliseconds since the search started
+    pub elapsed_ms: u64,
+    /// the line of play the engine expects, starting with `best_move`
+    pub pv: Vec<ChessMove>,
+    /// how full the transposition table is, in permille
+    pub hashfull: u32,
+}
+
+/// How the engine plays, as opposed to how long it gets to think, which is up to `SearchLimits`. Self-play
+/// matches pit engines with different options against each other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchOptions {
+    pub weights: EvalWeights,
+    /// how much worse than an even position a draw is for the side the engine plays
+    pub contempt: i32,
+    /// look positions up in the transposition table. without it, the search is plain alpha-beta
+    pub transposition_table: bool,
+    /// search a ply deeper after a check, up to `MAX_EXTENSIONS` times along a line
+    pub check_extensions: bool,
+    /// search quiet moves that come late in the move ordering shallower, and again at full depth if they turn
+    /// out to be good after all
+    pub late_move_reductions: bool,
+    /// skip captures in the quiescence search that can't raise alpha even with a margin to spare
+    pub delta_pruning: bool,
+    /// cut off positions where passing the move still scores at least beta, outside of pawn endings
+    pub null_move_pruning: bool,
+    /// cut off positions near the leaves whose static evaluation is far above beta
+    pub reverse_futility_pruning: bool,
+    /// skip quiet moves near the leaves when the static evaluation is far below alpha
+    pub futility_pruning: bool,
+    /// skip the quiet moves that come late in the move ordering near the leaves
+    pub late_move_pruning: bool,
+    /// search every move after the first with a null window, just to prove it isn't better, and only
+    /// search it again with the full window if it is
+    pub principal_variation_search: bool,
+    /// search each iteration of iterative deepening with a window around the last one's score, widening it
+    /// when the score falls outside
+    pub aspiration_windows: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            weights: WEIGHTS.clone(),
+            contempt: DEFAULT_CONTEMPT,
+            transposition_table: true,
+            check_extensions: true,
+            late_move_reductions: true,
+            delta_pruning: true,
+            null_move_pruning: true,
+            reverse_futility_pruning: true,
+            futility_pruning: true,
+            late_move_pruning: true,
+            principal_variation_search: true,
+            aspiration_windows: true,
+        }
+    }
+}
+
+struct Searcher<'a, C: Clock> {
+    transposition_table: &'a mut TranspositionTable,
+    pawn_table: PawnHashTable,
+    move_orderer: MoveOrderer,
+    nodes: u64,
+    seldepth: u8,
+    // triangular principal variation table: pv_table[ply] holds the best line found from that ply on
+    pv_table: Vec<Vec<ChessMove>>,
+    // the hash and halfmove clock of every position from the last capture or pawn move of the game up to
+    // the one being searched
+    path: Vec<(u64, u32)>,
+    // where the root is in `path`. the positions before it were played in the game
+    root_index: usize,
+    // set when a score depends on how its position was reached, through a draw by repetition or the fifty
+    // move rule somewhere below it. such a score can't go in the transposition table, since the same
+    // position reached another way might not be a draw at all
+    path_dependent: bool,
+    // set right before searching a null move, so that the reply doesn't pass straight back
+    after_null_move: bool,
+    limits: &'a SearchLimits,
+    options: &'a SearchOptions,
+    clock: &'a C,
+    start_ms: u64,
+    stop: &'a AtomicBool,
+    // cleared until depth 1 is done, so that there is a move to play however early the search is stopped
+    can_abort: bool,
+    aborted: bool,
+}
+
+impl<'a, C: Clock> Searcher<'a, C> {
+    fn new(
+        board: &Board,
+        history: &GameHistory,
+        transposition_table: &'a mut TranspositionTable,
+        limits: &'a SearchLimits,
+        options: &'a SearchOptions,
+        clock: &'a C,
+        stop: &'a AtomicBool,
+    ) -> Self {
+        let earlier_positions = history.hashes().len() as u32;
+        let mut path: Vec<(u64, u32)> = history
+            .hashes()
+            .iter()
+            .enumerate()
+            .map(|(index, hash)| {
+                let plies_ago = earlier_positions - index as u32;
+                (*hash, history.halfmove_clock().saturating_sub(plies_ago))
+            })
+            .collect();
+        path.push((board.get_hash(), history.halfmove_clock()));
+        Searcher {
+            transposition_table,
+            pawn_table: PawnHashTable::new(),
+            move_orderer: MoveOrderer::new(),
+            nodes: 0,
+            seldepth: 0,
+            pv_table: vec![Vec::new(); MAX_PLY],
+            root_index: path.len() - 1,
+            path,
+            path_dependent: false,
+            after_null_move: false,
+            limits,
+            options,
+            clock,
+            start_ms: clock.now_ms(),
+            stop,
+            can_abort: false,
+            aborted: false,
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.clock.now_ms().saturating_sub(self.start_ms)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.aborted || !self.can_abort {
+            return self.aborted;
+        }
+        if self
+            .limits
+            .max_nodes
+            .is_some_and(|max_nodes| self.nodes > max_nodes)
+        {
+            self.aborted = true;
+        } else if self.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            // reading the clock isn't free, so only do it every so often
+            let out_of_time = self
+                .limits
+                .hard_time_ms
+                .is_some_and(|hard_time_ms| self.elapsed_ms() >= hard_time_ms);
+            self.aborted = out_of_time || self.stop.load(Ordering::Relaxed);
+        }
+        self.aborted
+    }
+
+    /// Whether there is enough time left to be worth starting another iteration.
+    fn can_start_iteration(&self) -> bool {
+        let within_soft_time = self
+            .limits
+            .soft_time_ms
+            .is_none_or(|soft_time_ms| self.elapsed_ms() < soft_time_ms);
+        let within_node_limit = self
+            .limits
+            .max_nodes
+            .is_none_or(|max_nodes| self.nodes < max_nodes);
+        within_soft_time && within_node_limit && !self.stop.load(Ordering::Relaxed)
+    }
+
+    fn search(
+        &mut self,
+        board: &Board,
+        ply_remaining: u8,
+        ply_searched: u8,
+        num_extensions: u8,
+        mut alpha: i32,
+        beta: i32,
+    ) -> (i32, Option<ChessMove>) {
+        let after_null_move = std::mem::replace(&mut self.after_null_move, false);
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply_searched);
+        // whatever line was stored at this ply came from a different position
+        self.pv_table[ply_searched as usize].clear();
+        if self.should_stop() {
+            // the result of an aborted search is thrown away, so it doesn't matter what we return
+            return (0, None);
+        }
+        let orig_alpha = alpha;
+        /* base cases for search function */
+        /* 1. the game is drawn because of how we got here. the root always needs a move though */
+        if ply_searched > 0 && (self.is_repetition() || self.is_fifty_move_draw(board)) {
+            self.path_dependent = true;
+            return (self.draw_score(ply_searched), None);
+        }
+
+        /* 2. we have already seen this position before */
+        let mut hash_move = None;
+        if self.options.transposition_table {
+            let probe = self.transposition_table.probe(
+                board.get_hash(),
+                ply_remaining,
+                ply_searched,
+                alpha,
+                beta,
+            );
+            // the table outlives the game that filled it, so a stored score knows nothing of the draws the root's
+            // children could be. the root only takes its move to search first
+            if let Some(evaluation) = probe.cutoff.filter(|_| ply_searched > 0) {
+                return (evaluation, probe.best_move);
+            }
+            hash_move = probe.best_move;
+        }
+
+        /* 3. our game finished */
+        match board.status() {
+            // the side to move got checkmated. prefer faster mates by taking off the plies it took to get here
+            BoardStatus::Checkmate => return (-CHECKMATE_EVAL + ply_searched as i32, None),
+            BoardStatus::Stalemate => return (self.draw_score(ply_searched), None),
+            BoardStatus::Ongoing => {}
+        }
+
+        /* 4. we have reached 0 depth, so resolve any captures before trusting the static evaluation */
+        if ply_remaining == 0 {
+            return (
+                self.quiescence_search(board, alpha, beta, ply_searched),
+                None,
+            );
+        }
+
+        // whether our children's scores were path dependent, which would make ours path dependent too
+        let parent_path_dependent = std::mem::replace(&mut self.path_dependent, false);
+        let mut path_dependent = false;
+
+        // the pruning below guesses from the static evaluation, which means nothing in check. the root
+        // always searches every move
+        let in_check = board.checkers().popcnt() > 0;
+        let can_prune = ply_searched > 0 && !in_check;
+        let needs_static_eval = self.options.reverse_futility_pruning
+            || self.options.null_move_pruning
+            || self.options.futility_pruning;
+        let static_eval = if can_prune && needs_static_eval {
+            self.evaluate(board)
         } else {
-            quiescence_search(board, alpha, beta, move_orderer, ply_searched)
+            -CHECKMATE_EVAL
         };
-        transposition_table.add(
-            board.get_hash(),
-            evaluation,
-            ply_remaining,
-            Type::Exact,
-            None,
+
+        /* 5. we are so far above beta that even losing a margin for every ply left won't bring us down */
+        if self.options.reverse_futility_pruning
+            && can_prune
+            && ply_remaining <= REVERSE_FUTILITY_MAX_DEPTH
+            && !is_mate_eval(beta)
+            && static_eval - REVERSE_FUTILITY_MARGIN * ply_remaining as i32 >= beta
+        {
+            self.path_dependent = parent_path_dependent;
+            return (static_eval, None);
+        }
+
+        /* 6. we are still above beta after passing, so any real move would surely be too. in pawn endings
+        passing is often the best move there is (zugzwang), so it proves nothing there */
+        if self.options.null_move_pruning
+            && can_prune
+            && !after_null_move
+            && ply_remaining >= NULL_MOVE_MIN_DEPTH
+            && !is_mate_eval(beta)
+            && static_eval >= beta
+            && has_non_pawn_material(board)
+        {
+            if let Some(board_with_null_move) = board.null_move() {
+                let reduction = NULL_MOVE_REDUCTION + ply_remaining / 6;
+                // nothing before a pass can repeat after it, as far as the search is concerned
+                self.path.push((board_with_null_move.get_hash(), 0));
+                self.after_null_move = true;
+                let evaluation = -self
+                    .search(
+                        &board_with_null_move,
+                        ply_remaining.saturating_sub(1 + reduction),
+                        ply_searched + 1,
+                        num_extensions,
+                        -beta,
+                        -beta + 1,
+                    )
+                    .0;
+                self.path.pop();
+                let null_path_dependent = std::mem::replace(&mut self.path_dependent, false);
+                if self.aborted {
+                    return (0, None);
+                }
+                if evaluation >= beta {
+                    self.path_dependent = parent_path_dependent || null_path_dependent;
+                    // mating after passing doesn't mean we can mate for real
+                    let evaluation = if is_mate_eval(evaluation) {
+                        beta
+                    } else {
+                        evaluation
+                    };
+                    return (evaluation, None);
+                }
+            }
+        }
+
+        // this close to the leaves, quiet moves can't bring us back up to alpha, so only the first one is searched
+        let is_futile = self.options.futility_pruning
+            && can_prune
+            && ply_remaining <= FUTILITY_MAX_DEPTH
+            && !is_mate_eval(alpha)
+            && static_eval + FUTILITY_MARGIN * (ply_remaining as i32) <= alpha;
+
+        /* Generate all the legal moves and iterate over them */
+        /* Order moves first by looking at checks, then captures, then the remaining moves */
+        let moves: Vec<ChessMove> = self.move_orderer.order_moves(
+            MoveGen::new_legal(board).collect(),
+            board,
             ply_searched,
+            hash_move,
         );
-        return (evaluation, None);
-    }
-    /* Generate all the legal moves and iterate over them */
-    /* Order moves first by looking at checks, then captures, then the remaining moves */
-    let moves: Vec<ChessMove> =
-        move_orderer.order_moves(MoveGen::new_legal(board).collect(), board, ply_searched);
-
-    let mut best_val = if maximizing_player {
-        /* If we are the maximzing player (i.e. white), we want to get the move with the maximum evaluation,
-        so start with the minimum evaluation */
-        -CHECKMATE_EVAL
-    } else {
-        /* If we are the minimizing player (i.e. black), we want to get the move with the minimum evaluation,
-        so start with the maximum evaluation */
-        CHECKMATE_EVAL
-    };
-    let mut best_move = None;
-
-    for (i, legal_move) in moves.iter().enumerate() {
-        let board_with_move = board.make_move_new(*legal_move);
-        let mut curr_extension: u8 = 0;
-        // search extensions extend the search whenever our move checked the opponent's king (we want to
-        // look deeper into check moves since there are less possible responses by opponent so we can afford to go deeper)
-        if board_with_move.checkers().popcnt() > 0 && num_extensions < MAX_EXTENSIONS {
-            curr_extension = 1;
-        }
-        // if i >= 4, then we are towards the middle of our ordered-move list. as a result, we have said that these moves are
-        // less likely to be good moves since they were ordered less, so reduce the search depth for these branches
-        let search_minimization = if i >= 3 && ply_remaining > 1 { 1 } else { 0 };
-
-        let evaluation = search(
-            &board_with_move,
-            transposition_table,
-            move_orderer,
-            ply_remaining - 1 + curr_extension - search_minimization,
-            ply_searched + 1,
-            num_extensions + curr_extension,
-            alpha,
-            beta,
-            move_ply + 1,
-        )
-        .0;
-
-        if maximizing_player {
+
+        // every score is from the point of view of the side to move, so we always look for the maximum
+        let mut best_val = -CHECKMATE_EVAL;
+        let mut best_move = None;
+
+        for (i, legal_move) in moves.iter().enumerate() {
+            let board_with_move = board.make_move_new(*legal_move);
+            let is_quiet = is_quiet(board, *legal_move, &board_with_move);
+            if i > 0 && is_quiet {
+                if is_futile {
+                    // the move is worth at most this, which keeps an upper bound on our score honest
+                    best_val = best_val.max(static_eval + FUTILITY_MARGIN * ply_remaining as i32);
+                    continue;
+                }
+                if self.options.late_move_pruning
+                    && can_prune
+                    && ply_remaining <= LATE_MOVE_PRUNING_MAX_DEPTH
+                    && i >= late_move_pruning_count(ply_remaining)
+                {
+                    continue;
+                }
+            }
+            let mut curr_extension: u8 = 0;
+            // search extensions extend the search whenever our move checked the opponent's king (we want to
+            // look deeper into check moves since there are less possible responses by opponent so we can afford to go deeper)
+            if self.options.check_extensions
+                && board_with_move.checkers().popcnt() > 0
+                && num_extensions < MAX_EXTENSIONS
+            {
+                curr_extension = 1;
+            }
+            let depth = ply_remaining - 1 + curr_extension;
+            // quiet moves towards the end of the ordered move list are unlikely to be any good, so search them
+            // shallower, the more so the later they come and the deeper the search is
+            let reduction = if self.options.late_move_reductions
+                && is_quiet
+                && !in_check
+                && i >= LATE_MOVE_REDUCTION_MIN_MOVES
+                && ply_remaining >= LATE_MOVE_REDUCTION_MIN_DEPTH
+            {
+                late_move_reduction(ply_remaining, i).min(depth - 1)
+            } else {
+                0
+            };
+
+            // the first move is most likely the best, so the rest only have to show that they aren't better,
+            // which a null window does much more cheaply than working out by how much
+            let null_window = self.options.principal_variation_search && i > 0;
+            let first_beta = if null_window { alpha + 1 } else { beta };
+
+            // the opponent's best score is our worst, so flip both the window and the result
+            self.push_position(board, *legal_move, &board_with_move);
+            let mut evaluation = -self
+                .search(
+                    &board_with_move,
+                    depth - reduction,
+                    ply_searched + 1,
+                    num_extensions + curr_extension,
+                    -first_beta,
+                    -alpha,
+                )
+                .0;
+            if reduction > 0 && evaluation > alpha && !self.aborted {
+                // the move is better than its place in the ordering let on, so it gets a proper look
+                evaluation = -self
+                    .search(
+                        &board_with_move,
+                        depth,
+                        ply_searched + 1,
+                        num_extensions + curr_extension,
+                        -first_beta,
+                        -alpha,
+                    )
+                    .0;
+            }
+            if null_window && evaluation > alpha && evaluation < beta && !self.aborted {
+                // the move is better than the first after all, so it needs an exact score
+                evaluation = -self
+                    .search(
+                        &board_with_move,
+                        depth,
+                        ply_searched + 1,
+                        num_extensions + curr_extension,
+                        -beta,
+                        -alpha,
+                    )
+                    .0;
+            }
+            self.path.pop();
+            let child_path_dependent = std::mem::replace(&mut self.path_dependent, false);
+            path_dependent |= child_path_dependent;
+            if self.aborted {
+                return (0, None);
+            }
+
             if evaluation > best_val {
                 best_val = evaluation;
                 best_move = Some(*legal_move);
             }
-            alpha = i32::max(alpha, evaluation);
-        } else {
-            if evaluation < best_val {
-                best_val = evaluation;
-                best_move = Some(*legal_move);
+            if evaluation > alpha {
+                alpha = evaluation;
+                self.update_pv(*legal_move, ply_searched as usize);
             }
-            beta = i32::min(beta, evaluation);
-        }
 
-        //  if our alpha is >= beta, no need to search any further. PRUNE!
-        if alpha >= beta {
-            transposition_table.add(
-                board_with_move.get_hash(),
-                evaluation,
+            //  if our alpha is >= beta, no need to search any further. PRUNE!
+            if alpha >= beta {
+                // only the move that caused the cutoff matters for a lower bound
+                path_dependent = child_path_dependent;
+                // since we have an alpha beta cutoff, this could be a killer move if it isn't a capture
+                let is_capture_move = board
+                    .piece_on(Square::make_square(
+                        legal_move.get_dest().get_rank(),
+                        legal_move.get_dest().get_file(),
+                    ))
+                    .is_some();
+
+                if !is_capture_move {
+                    self.move_orderer.add_killer_move(*legal_move, ply_searched);
+                    // if there is a cutoff earlier in the search tree, then the ply_remaining will be greater
+                    // also, an earlier cutoff means an obviously bad move such as an opponent blundering a queen. because of this,
+                    // prioritize early cutoffs by squaring the ply_remaining so that history score is weighted more heavily in
+                    // favor of early cutoffs than late cutoffs, because a late cutoff could technically be not 100% accurate due to finite
+                    // search depth.
+                    let history_score = (ply_remaining as u32).saturating_mul(ply_remaining as u32);
+                    self.move_orderer.add_history(
+                        *legal_move,
+                        board.side_to_move() == Color::White,
+                        history_score,
+                    )
+                }
+                break;
+            }
+        }
+        // the window we were given decides what the score means: reaching beta makes it a lower bound, and not
+        // beating alpha an upper bound
+        let entry_type = Type::for_window(best_val, orig_alpha, beta);
+        if self.options.transposition_table && !path_dependent {
+            // every move failed low, so the best of them is only the least refuted and not worth trying first
+            let stored_move = if entry_type == Type::UpperBound {
+                None
+            } else {
+                best_move
+            };
+            self.transposition_table.add(
+                board.get_hash(),
+                best_val,
                 ply_remaining,
-                Type::LowerBound,
-                best_move,
+                entry_type,
+                stored_move,
                 ply_searched,
             );
-            // since we have an alpha beta cutoff, this could be a killer move if it isn't a capture
-            let is_capture_move = board
-                .piece_on(Square::make_square(
-                    legal_move.get_dest().get_rank(),
-                    legal_move.get_dest().get_file(),
-                ))
-                .is_some();
-
-            if !is_capture_move {
-                move_orderer.add_killer_move(*legal_move, ply_searched);
-                // if there is a cutoff earlier in the search tree, then the ply_remaining will be greater
-                // also, an earlier cutoff means an obviously bad move such as an opponent blundering a queen. because of this,
-                // prioritize early cutoffs by squaring the ply_remaining so that history score is weighted more heavily in
-                // favor of early cutoffs than late cutoffs, because a late cutoff could technically be not 100% accurate due to finite
-                // search depth.
-                let history_score = ply_remaining * ply_remaining;
-                move_orderer.add_history(*legal_move, maximizing_player, history_score)
-            }
-
-            return (best_val, best_move);
-        }
-    }
-    let entry_type = if best_val < orig_alpha {
-        Type::UpperBound
-    } else {
-        Type::Exact
-    };
-    transposition_table.add(
-        board.get_hash(),
-        best_val,
-        ply_remaining,
-        entry_type,
-        best_move,
-        ply_searched,
-    );
-    return (best_val, best_move);
-}
+        }
+        self.path_dependent = parent_path_dependent || path_dependent;
+        (best_val, best_move)
+    }
 
-fn quiescence_search(
-    board: &Board,
-    mut alpha: i32,
-    beta: i32,
-    move_orderer: &mut MoveOrderer,
-    ply_searched: u8,
-) -> i32 {
-    let evaluation = board_eval(board, 1_______________________1);
-    return evaluation;
-    if evaluation >= beta {
-        return beta; // cutoff - opposing player will not go down this path
-    }
-    if evaluation > alpha {
-        alpha = evaluation;
-    }
-    let mut moves_iter = MoveGen::new_legal(board);
-    let targets = board.color_combined(!board.side_to_move());
-    moves_iter.set_iterator_mask(*targets);
-    let moves: Vec<ChessMove> = move_orderer.order_moves(moves_iter.collect(), board, ply_searched);
-    for capture_move in moves {
-        let board_with_capture_move = board.make_move_new(capture_move);
-        let evaluation = quiescence_search(
-            &board_with_capture_move,
-            -beta,
-            -alpha,
-            move_orderer,
-            ply_searched + 1,
-        );
-        if evaluation >= beta {
-            return beta; // cutoff - opposing player will not go down this path
+    /// Searches the root with a window around `guess`, the score of the last iteration. A narrow window
+    /// prunes a lot more, but when the score falls outside of it, the search only finds out which side it
+    /// fell on, and has to be repeated with the window widened on that side.
+    fn aspiration_search(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        guess: i32,
+    ) -> (i32, Option<ChessMove>) {
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = (guess - delta).max(-CHECKMATE_EVAL);
+        let mut beta = (guess + delta).min(CHECKMATE_EVAL);
+        loop {
+            let (eval, best_move) = self.search(board, depth, 0, 0, alpha, beta);
+            // a bound that is already as wide as it goes can't be failed
+            let failed_low = eval <= alpha && alpha > -CHECKMATE_EVAL;
+            let failed_high = eval >= beta && beta < CHECKMATE_EVAL;
+            if self.aborted || !(failed_low || failed_high) {
+                return (eval, best_move);
+            }
+            delta = delta.saturating_mul(2);
+            if failed_low {
+                alpha = eval.saturating_sub(delta).max(-CHECKMATE_EVAL);
+            } else {
+                beta = eval.saturating_add(delta).min(CHECKMATE_EVAL);
+            }
+        }
+    }
+
+    /// Adds the position after `chess_move` to the end of the path.
+    fn push_position(&mut self, board: &Board, chess_move: ChessMove, board_with_move: &Board) {
+        let halfmove_clock = if is_irreversible(board, chess_move) {
+            0
+        } else {
+            self.path
+                .last()
+                .map_or(0, |(_, halfmove_clock)| halfmove_clock + 1)
+        };
+        self.path.push((board_with_move.get_hash(), halfmove_clock));
+    }
+
+    /// Whether the position at the end of the path repeats one before it. Once is enough if the earlier one
+    /// came up during the search, since whoever repeated it could just as well do it again. Positions from the
+    /// game have to have come up twice, for an actual threefold repetition.
+    fn is_repetition(&self) -> bool {
+        let current = self.path.len() - 1;
+        let (hash, halfmove_clock) = self.path[current];
+        // nothing from before the last capture or pawn move can repeat
+        let earliest = current.saturating_sub(halfmove_clock as usize);
+        let mut repetitions_in_game = 0;
+        // only positions with the same side to move can be the same
+        for index in (earliest..current.saturating_sub(1)).rev().step_by(2) {
+            if self.path[index].0 != hash {
+                continue;
+            }
+            if index > self.root_index {
+                return true;
+            }
+            repetitions_in_game += 1;
+            if repetitions_in_game == 2 {
+                return true;
+            }
         }
-        if alpha > evaluation {
-            alpha = evaluation;
+        false
+    }
+
+    /// Whether fifty moves have gone by without a capture or a pawn move. Checkmate on the last move still counts.
+    fn is_fifty_move_draw(&self, board: &Board) -> bool {
+        self.path
+            .last()
+            .is_some_and(|(_, halfmove_clock)| *halfmove_clock >= 100)
+            && board.status() != BoardStatus::Checkmate
+    }
+
+    /// The score of a draw for the side to move. The engine, which is to move at the root, would rather play on
+    /// unless it is worse off by more than the contempt.
+    fn draw_score(&self, ply_searched: u8) -> i32 {
+        if ply_searched.is_multiple_of(2) {
+            -self.options.contempt
+        } else {
+            self.options.contempt
         }
     }
-    return alpha;
+
+    /// Keeps searching captures (and promotions) past the end of the main search so that we never
+    /// evaluate a position in the middle of an exchange. When in check, every evasion is searched instead
+    /// since standing pat isn't an option.
+    fn quiescence_search(
+        &mut self,
+        board: &Board,
+        mut alpha: i32,
+        beta: i32,
+        ply_searched: u8,
+    ) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply_searched);
+        if self.should_stop() {
+            return 0;
+        }
+        let in_check = board.checkers().popcnt() > 0;
+
+        let mut moves_iter = MoveGen::new_legal(board);
+        let stand_pat = if in_check {
+            // every legal move gets searched, and if there are none we are checkmated
+            if moves_iter.len() == 0 {
+                return -CHECKMATE_EVAL + ply_searched as i32;
+            }
+            -CHECKMATE_EVAL
+        } else {
+            // we are never forced to capture, so the static evaluation is a lower bound on our score
+            let stand_pat = self.evaluate(board);
+            if stand_pat >= beta {
+                return beta; // cutoff - opposing player will not go down this path
+            }
+            // delta pruning: if even winning a queen for free can't raise alpha, don't bother looking
+            if self.options.delta_pruning
+                && stand_pat + self.piece_value(Piece::Queen) + DELTA_MARGIN < alpha
+            {
+                return alpha;
+            }
+            stand_pat
+        };
+        alpha = i32::max(alpha, stand_pat);
+
+        let mut moves: Vec<ChessMove> = Vec::new();
+        if in_check {
+            moves.extend(&mut moves_iter);
+        } else {
+            // captures first, including en passant which lands on an empty square
+            let mut targets = *board.color_combined(!board.side_to_move());
+            if let Some(en_passant_square) = board.en_passant() {
+                targets |=
+                    BitBoard::from_square(en_passant_square.ubackward(!board.side_to_move()));
+            }
+            moves_iter.set_iterator_mask(targets);
+            moves.extend(&mut moves_iter);
+            // then pushes onto the last rank. only pawns moving there are promotions
+            let promotion_squares =
+                get_rank(board.side_to_move().to_their_backrank()) & !board.combined();
+            moves_iter.set_iterator_mask(promotion_squares);
+            moves.extend((&mut moves_iter).filter(|m| m.get_promotion().is_some()));
+        }
+        let moves = self
+            .move_orderer
+            .order_moves(moves, board, ply_searched, None);
+
+        for capture_move in moves {
+            if self.options.delta_pruning && !in_check && capture_move.get_promotion().is_none() {
+                // delta pruning for a single capture: skip it if winning the piece still leaves us below alpha
+                let captured_piece = board
+                    .piece_on(capture_move.get_dest())
+                    .unwrap_or(Piece::Pawn);
+                if stand_pat + self.piece_value(captured_piece) + DELTA_MARGIN < alpha {
+                    continue;
+                }
+            }
+            let board_with_capture_move = board.make_move_new(capture_move);
+            let evaluation =
+                -self.quiescence_search(&board_with_capture_move, -beta, -alpha, ply_searched + 1);
+            if self.aborted {
+                return 0;
+            }
+            if evaluation >= beta {
+                return beta; // cutoff - opposing player will not go down this path
+            }
+            alpha = i32::max(alpha, evaluation);
+        }
+        alpha
+    }
+
+    /// Our line from `ply` on is now `chess_move` followed by the line the child position found.
+    fn update_pv(&mut self, chess_move: ChessMove, ply: usize) {
+        let (parent, children) = self.pv_table.split_at_mut(ply + 1);
+        let pv = &mut parent[ply];
+        pv.clear();
+        pv.push(chess_move);
+        if let Some(child_pv) = children.first() {
+            pv.extend_from_slice(child_pv);
+        }
+    }
+
+    /// What the weights in use think a piece is worth.
+    fn piece_value(&self, piece: Piece) -> i32 {
+        get_count_of_piece_with_weights(piece, &self.options.weights) as i32
+    }
+
+    /// The static evaluation from the point of view of the side to move.
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let color_multiplier = if board.side_to_move() == Color::White {
+            1
+        } else {
+            -1
+        };
+        board_eval_with_pawn_table(board, &self.options.weights, &mut self.pawn_table)
+            * color_multiplier
+    }
 }
 
-pub fn choose_move(board: &Board, move_ply: u32) -> Option<ChessMove> {
-    let mut eval = 0;
-    let mut ai_move = None;
-    let mut transposition_table = TranspositionTable::new();
-    let mut move_orderer = MoveOrderer::new();
-    for depth in 1..(MAX_DEPTH + 1) {
-        (eval, ai_move) = search(
-            board,
-            &mut transposition_table,
-            &mut move_orderer,
+/// A move that doesn't capture, promote or give check, which is what the pruning and reductions go after.
+fn is_quiet(board: &Board, chess_move: ChessMove, board_with_move: &Board) -> bool {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let is_en_passant =
+        board.piece_on(source) == Some(Piece::Pawn) && source.get_file() != dest.get_file();
+    board.piece_on(dest).is_none()
+        && !is_en_passant
+        && chess_move.get_promotion().is_none()
+        && board_with_move.checkers().popcnt() == 0
+}
+
+/// Whether the side to move has anything besides pawns, without which zugzwang is common.
+fn has_non_pawn_material(board: &Board) -> bool {
+    let pawns_and_kings = board.pieces(Piece::Pawn) | board.pieces(Piece::King);
+    (board.color_combined(board.side_to_move()) & !pawns_and_kings).popcnt() > 0
+}
+
+/// How many moves get searched with `ply_remaining` plies left before late move pruning skips the quiet ones.
+fn late_move_pruning_count(ply_remaining: u8) -> usize {
+    3 + (ply_remaining as usize).pow(2)
+}
+
+/// How many plies shallower the move at `move_index` is searched with `ply_remaining` plies left.
+fn late_move_reduction(ply_remaining: u8, move_index: usize) -> u8 {
+    (0.75 + (ply_remaining as f32).ln() * (move_index as f32).ln() / 2.25) as u8
+}
+
+/// Runs iterative deepening until one of `limits` is hit, calling `on_iteration` after every completed depth.
+/// Setting `stop` aborts the search, though depth 1 is always finished. Either way, the result of the last
+/// completed depth is returned. `history` is the game that led up to `board`, so that the search can steer
+/// into or away from repetitions. Keeping `transposition_table` from one move of a game to the next saves the
+/// search from starting over every time.
+#[allow(clippy::too_many_arguments)]
+pub fn iterative_deepening<C: Clock, F: FnMut(&SearchResult)>(
+    board: &Board,
+    history: &GameHistory,
+    limits: &SearchLimits,
+    options: &SearchOptions,
+    transposition_table: &mut TranspositionTable,
+    clock: &C,
+    stop: &AtomicBool,
+    mut on_iteration: F,
+) -> SearchResult {
+    let mut result = SearchResult {
+        best_move: None,
+        score: Score::Centipawns(0),
+        depth: 0,
+        seldepth: 0,
+        nodes: 0,
+        elapsed_ms: 0,
+        pv: Vec::new(),
+        hashfull: 0,
+    };
+    transposition_table.new_search();
+    let mut searcher = Searcher::new(
+        board,
+        history,
+        transposition_table,
+        limits,
+        options,
+        clock,
+        stop,
+    );
+    for depth in 1..(limits.max_depth + 1) {
+        // always finish depth 1 so that we have a move to play
+        if depth > 1 && !searcher.can_start_iteration() {
+            break;
+        }
+        let last_eval = match result.score {
+            Score::Centipawns(centipawns) => Some(centipawns),
+            // mate scores jump around too much from one depth to the next to guess from
+            Score::Mate(_) => None,
+        };
+        let (eval, best_move) = match last_eval {
+            Some(guess) if options.aspiration_windows && depth >= ASPIRATION_MIN_DEPTH => {
+                searcher.aspiration_search(board, depth, guess)
+            }
+            _ => searcher.search(board, depth, 0, 0, -CHECKMATE_EVAL, CHECKMATE_EVAL),
+        };
+        if searcher.aborted {
+            break;
+        }
+        let mut pv = searcher.pv_table[0].clone();
+        if pv.first() != best_move.as_ref() {
+            // the root came straight out of the transposition table, so we don't know the rest of the line
+            pv = best_move.into_iter().collect();
+        }
+        result = SearchResult {
+            best_move,
+            score: Score::from_eval(eval),
             depth,
-            0,
-            0,
-            -CHECKMATE_EVAL,
-            CHECKMATE_EVAL,
-            move_ply,
-        );
+            seldepth: searcher.seldepth,
+            nodes: searcher.nodes,
+            elapsed_ms: searcher.elapsed_ms(),
+            pv,
+            hashfull: searcher.transposition_table.hashfull(),
+        };
+        on_iteration(&result);
+        searcher.can_abort = true;
     }
-    log!(eval);
 
-    if ai_move.is_none() {
-        log!("I can't find a good move to save me...");
-        return MoveGen::new_legal(board).next();
-    }
-    ai_move
+    result.nodes = searcher.nodes;
+    result.elapsed_ms = searcher.elapsed_ms();
+    result.hashfull = searcher.transposition_table.hashfull();
+    result
+}
+
+pub fn choose_move(board: &Board, history: &GameHistory) -> SearchResult {
+    let stop = AtomicBool::new(false);
+    let limits = SearchLimits::move_time(AI_MOVE_TIME_MS);
+    let options = SearchOptions::default();
+    let mut transposition_table = TranspositionTable::new(DEFAULT_HASH_MB);
+    iterative_deepening(
+        board,
+        history,
+        &limits,
+        &options,
+        &mut transposition_table,
+        &SystemClock,
+        &stop,
+        |_result| {
+            #[cfg(target_arch = "wasm32")]
+            gloo_console::log!(format!(
+                "depth {} score {:?} pv {}",
+                _result.depth,
+                _result.score,
+                _result
+                    .pv
+                    .iter()
+                    .map(|chess_move| chess_move.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ));
+        },
+    )
+}
+
+/// The score of a quiescence search from `board`, from the point of view of the side to move. This only
+/// differs from the static evaluation when there are captures to be made, so it tells quiet positions apart.
+pub fn quiescence_eval(board: &Board) -> i32 {
+    let stop = AtomicBool::new(false);
+    let limits = SearchLimits::infinite();
+    let options = SearchOptions::default();
+    // the quiescence search doesn't use the table, so there's no point making it big
+    let mut transposition_table = TranspositionTable::new(0);
+    let mut searcher = Searcher::new(
+        board,
+        &GameHistory::default(),
+        &mut transposition_table,
+        &limits,
+        &options,
+        &SystemClock,
+        &stop,
+    );
+    searcher.quiescence_search(board, -CHECKMATE_EVAL, CHECKMATE_EVAL, 0)
 }
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{MAX_SEARCH_DEPTH, MOVE_OVERHEAD_MS};
+
+// when playing on a clock without a moves-to-go count, assume there are this many moves left in the game
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// A source of wall-clock time in milliseconds. The epoch doesn't matter, only differences between
+/// two readings are used. This lets the search run natively and in the browser, and lets tests fake time.
+pub trait Clock {
+    fn now_ms(&self) -> u64;
+}
+
+/// The real clock of whatever platform we are running on.
+#[derive(Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        use std::{sync::OnceLock, time::Instant};
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_millis() as u64
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        // std::time::Instant panics on wasm32-unknown-unknown, so ask javascript instead
+        js_sys::Date::now() as u64
+    }
+}
+
+/// Everything that can make iterative deepening stop, besides an explicit stop request.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchLimits {
+    pub max_depth: u8,
+    pub max_nodes: Option<u64>,
+    /// once this many milliseconds have passed, don't start another iteration
+    pub soft_time_ms: Option<u64>,
+    /// once this many milliseconds have passed, abort the iteration in progress
+    pub hard_time_ms: Option<u64>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits::infinite()
+    }
+}
+
+impl SearchLimits {
+    /// Search until told to stop.
+    pub fn infinite() -> SearchLimits {
+        SearchLimits {
+            max_depth: MAX_SEARCH_DEPTH,
+            max_nodes: None,
+            soft_time_ms: None,
+            hard_time_ms: None,
+        }
+    }
+
+    /// Search every iteration up to and including `max_depth`.
+    pub fn depth(max_depth: u8) -> SearchLimits {
+        SearchLimits {
+            max_depth: max_depth.clamp(1, MAX_SEARCH_DEPTH),
+            ..SearchLimits::infinite()
+        }
+    }
+
+    /// Search for at most `move_time_ms` milliseconds.
+    pub fn move_time(move_time_ms: u64) -> SearchLimits {
+        let budget = move_time_ms.saturating_sub(MOVE_OVERHEAD_MS).max(1);
+        SearchLimits {
+            soft_time_ms: Some(budget),
+            hard_time_ms: Some(budget),
+            ..SearchLimits::infinite()
+        }
+    }
+
+    /// Divide up the time left on our clock. We aim to use `soft_time_ms` for this move, but will
+    /// let an iteration that's already running go on until `hard_time_ms` before giving up on it.
+    pub fn from_clock(
+        time_left_ms: u64,
+        increment_ms: u64,
+        moves_to_go: Option<u64>,
+    ) -> SearchLimits {
+        let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let usable_time = time_left_ms.saturating_sub(MOVE_OVERHEAD_MS).max(1);
+        // never burn more than a quarter of the clock on a single move, however big the increment is
+        let max_time = usable_time / 4;
+        let soft_time = (time_left_ms / moves_to_go + increment_ms * 3 / 4).min(max_time);
+        let hard_time = (soft_time * 3).min(max_time).max(soft_time);
+        SearchLimits {
+            soft_time_ms: Some(soft_time.max(1)),
+            hard_time_ms: Some(hard_time.max(1)),
+            ..SearchLimits::infinite()
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u8) -> SearchLimits {
+        self.max_depth = max_depth.clamp(1, MAX_SEARCH_DEPTH);
+        self
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> SearchLimits {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+}
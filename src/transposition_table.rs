@@ -1,69 +1,151 @@
-use std::cmp::Ordering;
-
-use chess::{CacheTable, ChessMove};
+use chess::ChessMove;
 
 use crate::constants::CHECKMATE_EVAL;
 
-#[derive(Copy, Clone, Default, PartialEq)]
+/// What a stored score says about the true score of its position. Like every score in the search, both are
+/// from the point of view of the side to move.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub enum Type {
+    /// the score fell inside the window, so it is the true score
     #[default]
     Exact,
+    /// the search failed low: no move beat alpha, so the true score is at most this
     UpperBound,
+    /// the search failed high: a move reached beta and the rest were skipped, so the true score is at least this
     LowerBound,
 }
 
+impl Type {
+    /// What a search of the window from `alpha` to `beta` that returned `score` found out.
+    pub fn for_window(score: i32, alpha: i32, beta: i32) -> Type {
+        if score >= beta {
+            Type::LowerBound
+        } else if score <= alpha {
+            Type::UpperBound
+        } else {
+            Type::Exact
+        }
+    }
+
+    /// Whether a stored `score` of this type answers a search of the window from `alpha` to `beta`, which
+    /// is when the search would have returned it too.
+    pub fn settles(self, score: i32, alpha: i32, beta: i32) -> bool {
+        match self {
+            Type::Exact => true,
+            Type::UpperBound => score <= alpha,
+            Type::LowerBound => score >= beta,
+        }
+    }
+}
+
+/// What the table knows about a position.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Probe {
+    /// the stored score, if it was searched deep enough and its bound settles the window
+    pub cutoff: Option<i32>,
+    /// the best move found last time, which is worth searching first even when the score is no use
+    pub best_move: Option<ChessMove>,
+}
+
 #[derive(Copy, Clone, Default)]
 struct Entry {
+    // the full hash of the position, since many positions share a bucket. zero marks an empty slot
+    key: u64,
     evaluation: i32,
     best_move: Option<ChessMove>,
     entry_type: Type,
     depth: u8,
+    // the search that stored the entry, so that entries left over from earlier moves can be replaced
+    generation: u8,
 }
 
-impl PartialEq for Entry {
-    fn eq(&self, other: &Self) -> bool {
-        self.evaluation == other.evaluation
-    }
-}
-
-impl PartialOrd for Entry {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.evaluation.partial_cmp(&other.evaluation)
-    }
+// positions that hash to the same bucket compete for its two slots. the first keeps whichever entry took the
+// most work to find, and the second always takes the newest entry, so that deep results survive a flood of
+// shallow ones without the table filling up with stale entries
+#[derive(Copy, Clone, Default)]
+struct Bucket {
+    depth_preferred: Entry,
+    always_replace: Entry,
 }
 
+/// Remembers the results of earlier searches by position. It can be kept from one move of a game to the
+/// next, as long as `new_search` is called before every search.
 pub struct TranspositionTable {
-    tt: CacheTable<Entry>,
+    buckets: Vec<Bucket>,
+    generation: u8,
 }
 
 impl TranspositionTable {
-    pub fn new() -> TranspositionTable {
+    /// A table that takes up about `size_mb` megabytes.
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let num_buckets = (size_mb * 1024 * 1024 / std::mem::size_of::<Bucket>()).max(1);
         TranspositionTable {
-            tt: CacheTable::new(32768, Entry::default()),
+            buckets: vec![Bucket::default(); num_buckets],
+            generation: 0,
         }
     }
-    pub fn get(
+
+    /// Forgets everything, like at the start of a new game.
+    pub fn clear(&mut self) {
+        self.buckets.fill(Bucket::default());
+        self.generation = 0;
+    }
+
+    /// Marks everything stored so far as coming from an earlier search, which lets it be replaced first.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// How full the table is with entries from the current search, in permille, going by the first thousand
+    /// slots.
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.buckets[..self.buckets.len().min(500)];
+        let used = sample
+            .iter()
+            .flat_map(|bucket| [bucket.depth_preferred, bucket.always_replace])
+            .filter(|entry| entry.key != 0 && entry.generation == self.generation)
+            .count();
+        (used * 1000 / (sample.len() * 2)) as u32
+    }
+
+    fn bucket_index(&self, zobrist_hash: u64) -> usize {
+        // maps the hash onto the buckets evenly without needing a power of two of them
+        ((zobrist_hash as u128 * self.buckets.len() as u128) >> 64) as usize
+    }
+
+    fn find(&self, zobrist_hash: u64) -> Option<Entry> {
+        let bucket = &self.buckets[self.bucket_index(zobrist_hash)];
+        [bucket.depth_preferred, bucket.always_replace]
+            .into_iter()
+            .find(|entry| entry.key == zobrist_hash)
+    }
+
+    /// Looks up a position that is about to be searched `depth` plies deep with the window from `alpha` to
+    /// `beta`.
+    pub fn probe(
         &self,
         zobrist_hash: u64,
         depth: u8,
         ply_searched: u8,
         alpha: i32,
         beta: i32,
-    ) -> Option<(i32, Option<ChessMove>)> {
-        if let Some(entry) = self.tt.get(zobrist_hash) {
-            if entry.depth < depth {
-                return None; // we haven't evaluated this position before at the specified depth
-            }
-            let corrected_evaluation = get_optimized_mate_score(entry.evaluation, ply_searched);
-            if entry.entry_type == Type::Exact
-                || (entry.entry_type == Type::UpperBound && corrected_evaluation <= alpha)
-                || (entry.entry_type == Type::LowerBound && corrected_evaluation >= beta)
-            {
-                return Some((corrected_evaluation, entry.best_move));
-            }
+    ) -> Probe {
+        let Some(entry) = self.find(zobrist_hash) else {
+            return Probe::default();
+        };
+        let corrected_evaluation = get_optimized_mate_score(entry.evaluation, ply_searched);
+        // a shallower search of the position can't stand in for this one, but its best move is still a good guess
+        let cutoff = (entry.depth >= depth
+            && entry.entry_type.settles(corrected_evaluation, alpha, beta))
+        .then_some(corrected_evaluation);
+        Probe {
+            cutoff,
+            best_move: entry.best_move,
         }
-        return None;
     }
+
+    /// Stores the result of searching a position `depth` plies deep. `entry_type` should come from
+    /// `Type::for_window` with the window the search was given.
     pub fn add(
         &mut self,
         zobrist_hash: u64,
@@ -73,15 +155,30 @@ impl TranspositionTable {
         best_move: Option<ChessMove>,
         ply_searched: u8,
     ) {
-        self.tt.add(
-            zobrist_hash,
-            Entry {
-                evaluation: store_optimized_mate_score(evaluation, ply_searched),
-                best_move,
-                depth,
-                entry_type,
-            },
-        );
+        let generation = self.generation;
+        let index = self.bucket_index(zobrist_hash);
+        let bucket = &mut self.buckets[index];
+        let slot = if bucket.depth_preferred.key == zobrist_hash
+            || bucket.depth_preferred.generation != generation
+            || depth >= bucket.depth_preferred.depth
+        {
+            &mut bucket.depth_preferred
+        } else {
+            &mut bucket.always_replace
+        };
+        // a search that failed low doesn't know a best move, but an earlier one of this position might have
+        let best_move = match best_move {
+            None if slot.key == zobrist_hash => slot.best_move,
+            _ => best_move,
+        };
+        *slot = Entry {
+            key: zobrist_hash,
+            evaluation: store_optimized_mate_score(evaluation, ply_searched),
+            best_move,
+            entry_type,
+            depth,
+            generation,
+        };
     }
 }
 
@@ -95,11 +192,11 @@ is crucial because the depth of the search changes as the ai explores different
 #[inline]
 fn store_optimized_mate_score(eval: i32, ply_searched: u8) -> i32 {
     let sign = if eval < 0 { -1 } else { 1 };
-    return if is_mate_eval(eval) {
+    if is_mate_eval(eval) {
         sign * (sign * eval + ply_searched as i32)
     } else {
         eval
-    };
+    }
 }
 /*
 Meanwhile, when a mate score is retrieved, it needs to be converted back to a relative value, i.e.,
@@ -112,14 +209,27 @@ slower mates i.e. M2 over M6
 #[inline]
 fn get_optimized_mate_score(eval: i32, ply_searched: u8) -> i32 {
     let sign = if eval < 0 { -1 } else { 1 };
-    return if is_mate_eval(eval) {
+    if is_mate_eval(eval) {
         sign * (sign * eval - ply_searched as i32)
     } else {
         eval
-    };
+    }
 }
 
 #[inline]
-fn is_mate_eval(eval: i32) -> bool {
+pub(crate) fn is_mate_eval(eval: i32) -> bool {
     eval.abs() > CHECKMATE_EVAL - 100 // extra leeway
 }
+
+/*
+A mate score found at the root is CHECKMATE_EVAL minus the number of plies it takes to deliver the mate,
+so this undoes that. The result is positive when the side to move is the one delivering mate.
+*/
+#[inline]
+pub(crate) fn plies_to_mate(eval: i32) -> Option<i32> {
+    if is_mate_eval(eval) {
+        Some(eval.signum() * (CHECKMATE_EVAL - eval.abs()))
+    } else {
+        None
+    }
+}
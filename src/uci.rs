@@ -0,0 +1,384 @@
+use std::{
+    error::Error,
+    fs,
+    io::Write,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chess::{Board, ChessMove, Color};
+
+use crate::{
+    constants::{AI_MOVE_TIME_MS, DEFAULT_CONTEMPT, DEFAULT_HASH_MB},
+    fen::{board_from_fen, halfmove_clock},
+    game_history::GameHistory,
+    opening_book::{default_book, opening_book_move, OpeningBook, TextBook},
+    polyglot::PolyglotBook,
+    search::{iterative_deepening, Score, SearchOptions, SearchResult},
+    search_limits::{SearchLimits, SystemClock},
+    transposition_table::TranspositionTable,
+};
+
+const ENGINE_NAME: &str = "Rust Chess";
+const ENGINE_AUTHOR: &str = "x2dtu";
+const MAX_HASH_MB: usize = 4096;
+
+/// The parameters of a `go` command.
+#[derive(Default)]
+struct GoOptions {
+    depth: Option<u8>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movestogo: Option<u64>,
+    nodes: Option<u64>,
+    infinite: bool,
+}
+
+impl GoOptions {
+    fn parse(tokens: &[&str]) -> GoOptions {
+        let mut options = GoOptions::default();
+        let mut tokens = tokens.iter();
+        while let Some(token) = tokens.next() {
+            let mut next_number = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+            match *token {
+                "depth" => {
+                    options.depth = next_number().map(|depth| depth.min(u8::MAX as u64) as u8)
+                }
+                "movetime" => options.movetime = next_number(),
+                "wtime" => options.wtime = next_number(),
+                "btime" => options.btime = next_number(),
+                "winc" => options.winc = next_number(),
+                "binc" => options.binc = next_number(),
+                "movestogo" => options.movestogo = next_number(),
+                "nodes" => options.nodes = next_number(),
+                "infinite" => options.infinite = true,
+                _ => {}
+            }
+        }
+        options
+    }
+
+    /// Turns the `go` parameters into limits for the search. Without any limits we search until
+    /// told to stop, but given only the opponent's clock we still move in reasonable time.
+    fn search_limits(&self, side_to_move: Color) -> SearchLimits {
+        let (time, increment) = if side_to_move == Color::White {
+            (self.wtime, self.winc)
+        } else {
+            (self.btime, self.binc)
+        };
+        let mut limits = if self.infinite {
+            SearchLimits::infinite()
+        } else if let Some(movetime) = self.movetime {
+            SearchLimits::move_time(movetime)
+        } else if let Some(time) = time {
+            SearchLimits::from_clock(time, increment.unwrap_or(0), self.movestogo)
+        } else if self.wtime.is_some() || self.btime.is_some() {
+            SearchLimits::move_time(AI_MOVE_TIME_MS)
+        } else {
+            SearchLimits::infinite()
+        };
+        if let Some(depth) = self.depth {
+            limits = limits.with_max_depth(depth);
+        }
+        if let Some(nodes) = self.nodes {
+            limits = limits.with_max_nodes(nodes);
+        }
+        limits
+    }
+}
+
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// A UCI front-end for the engine. Feed it one line of input at a time with `handle_command`;
+/// responses are written to standard output.
+pub struct UciEngine {
+    board: Board,
+    // the game leading up to `board`, which the search needs to know about repetitions
+    history: GameHistory,
+    contempt: i32,
+    // kept between searches, so that what we worked out on one move helps with the next
+    transposition_table: Arc<Mutex<TranspositionTable>>,
+    use_opening_book: bool,
+    // a book loaded through the BookFile option, used instead of the built in one
+    book_file: Option<Box<dyn OpeningBook>>,
+    running_search: Option<RunningSearch>,
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        UciEngine::new()
+    }
+}
+
+impl UciEngine {
+    pub fn new() -> UciEngine {
+        UciEngine {
+            board: Board::default(),
+            history: GameHistory::default(),
+            contempt: DEFAULT_CONTEMPT,
+            transposition_table: Arc::new(Mutex::new(TranspositionTable::new(DEFAULT_HASH_MB))),
+            use_opening_book: true,
+            book_file: None,
+            running_search: None,
+        }
+    }
+
+    /// Handles a single line of UCI input. Returns `false` once the engine has been told to quit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, arguments)) = tokens.split_first() else {
+            return true;
+        };
+        match *command {
+            "uci" => {
+                send(&format!("id name {}", ENGINE_NAME));
+                send(&format!("id author {}", ENGINE_AUTHOR));
+                send(&format!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB, MAX_HASH_MB
+                ));
+                send("option name OwnBook type check default true");
+                send("option name BookFile type string default <empty>");
+                send(&format!(
+                    "option name Contempt type spin default {} min -1000 max 1000",
+                    DEFAULT_CONTEMPT
+                ));
+                send("uciok");
+            }
+            "isready" => {
+                // load the opening book now rather than in the middle of our first search
+                if let Err(error) = default_book() {
+                    send(&format!(
+                        "info string could not load opening book: {}",
+                        error
+                    ));
+                }
+                send("readyok");
+            }
+            "setoption" => self.set_option(arguments),
+            "ucinewgame" => {
+                self.stop_search();
+                self.board = Board::default();
+                self.history = GameHistory::default();
+                self.lock_transposition_table().clear();
+            }
+            "position" => {
+                self.stop_search();
+                self.set_position(arguments);
+            }
+            "go" => {
+                self.stop_search();
+                self.go(GoOptions::parse(arguments));
+            }
+            "stop" => self.stop_search(),
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn set_option(&mut self, arguments: &[&str]) {
+        // setoption name <id> [value <x>]
+        let Some(value_index) = arguments.iter().position(|&token| token == "value") else {
+            return;
+        };
+        let name = arguments[1.min(value_index)..value_index].join(" ");
+        let value = arguments[value_index + 1..].join(" ");
+        if name.eq_ignore_ascii_case("OwnBook") {
+            self.use_opening_book = value.eq_ignore_ascii_case("true");
+        } else if name.eq_ignore_ascii_case("BookFile") {
+            match load_book_file(&value) {
+                Ok(book_file) => self.book_file = book_file,
+                Err(error) => send(&format!("info string could not load {}: {}", value, error)),
+            }
+        } else if name.eq_ignore_ascii_case("Hash") {
+            if let Ok(size_mb) = value.parse::<usize>() {
+                self.stop_search();
+                *self.lock_transposition_table() =
+                    TranspositionTable::new(size_mb.clamp(1, MAX_HASH_MB));
+            }
+        } else if name.eq_ignore_ascii_case("Contempt") {
+            if let Ok(contempt) = value.parse::<i32>() {
+                self.contempt = contempt.clamp(-1000, 1000);
+            }
+        }
+    }
+
+    /// Sets up the position of a `position` command. A bad move leaves us at the position before the move
+    /// list, and a bad position where we were, and either is reported rather than searched past.
+    fn set_position(&mut self, arguments: &[&str]) {
+        let moves_index = arguments
+            .iter()
+            .position(|&token| token == "moves")
+            .unwrap_or(arguments.len());
+        let (start, halfmove_clock) = match parse_position(&arguments[..moves_index]) {
+            Ok(position) => position,
+            Err(error) => {
+                send(&format!("info string {}", error));
+                return;
+            }
+        };
+        self.board = start;
+        self.history = GameHistory::new(halfmove_clock);
+        for move_text in arguments.iter().skip(moves_index + 1) {
+            let chess_move = ChessMove::from_str(move_text)
+                .ok()
+                .filter(|&chess_move| self.board.legal(chess_move));
+            let Some(chess_move) = chess_move else {
+                send(&format!(
+                    "info string illegal move {}, ignoring the move list",
+                    move_text
+                ));
+                self.board = start;
+                self.history = GameHistory::new(halfmove_clock);
+                return;
+            };
+            self.history.push(&self.board, chess_move);
+            self.board = self.board.make_move_new(chess_move);
+        }
+    }
+
+    fn book_move(&self) -> Option<ChessMove> {
+        match &self.book_file {
+            Some(book_file) => book_file.choose_move(&self.board),
+            None => opening_book_move(&self.board),
+        }
+    }
+
+    fn go(&mut self, options: GoOptions) {
+        if self.use_opening_book && !options.infinite {
+            if let Some(book_move) = self.book_move() {
+                send(&format!("bestmove {}", book_move));
+                return;
+            }
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let board = self.board;
+        let history = self.history.clone();
+        let contempt = self.contempt;
+        let transposition_table = self.transposition_table.clone();
+        let limits = options.search_limits(board.side_to_move());
+        let infinite = options.infinite;
+        let search_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            let options = SearchOptions {
+                contempt,
+                ..SearchOptions::default()
+            };
+            let mut transposition_table = transposition_table
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let result = iterative_deepening(
+                &board,
+                &history,
+                &limits,
+                &options,
+                &mut transposition_table,
+                &SystemClock,
+                &search_stop,
+                |info| send(&format_info(info)),
+            );
+            // in infinite mode we may not print our move until we are told to stop
+            while infinite && !search_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            match result.best_move {
+                Some(best_move) => send(&format!("bestmove {}", best_move)),
+                None => send("bestmove 0000"),
+            }
+        });
+        self.running_search = Some(RunningSearch { stop, handle });
+    }
+
+    fn lock_transposition_table(&self) -> std::sync::MutexGuard<'_, TranspositionTable> {
+        // only one search runs at a time, and we always wait for it to finish before touching the table
+        self.transposition_table
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(running_search) = self.running_search.take() {
+            running_search.stop.store(true, Ordering::Relaxed);
+            let _ = running_search.handle.join();
+        }
+    }
+}
+
+/// Loads a Polyglot book if the file ends in .bin, or a book in the Book.txt format otherwise.
+/// An empty path means going back to the built in book.
+fn load_book_file(path: &str) -> Result<Option<Box<dyn OpeningBook>>, Box<dyn Error>> {
+    if path.is_empty() || path == "<empty>" {
+        return Ok(None);
+    }
+    if path.ends_with(".bin") {
+        let book = PolyglotBook::read(fs::File::open(path)?)?;
+        Ok(Some(Box::new(book)))
+    } else {
+        let book = TextBook::parse(&fs::read_to_string(path)?)?;
+        Ok(Some(Box::new(book)))
+    }
+}
+
+/// Parses the position of a `position` command, up to its move list, into a board and its halfmove clock.
+fn parse_position(arguments: &[&str]) -> Result<(Board, u32), String> {
+    match arguments.split_first() {
+        Some((&"startpos", _)) => Ok((Board::default(), 0)),
+        Some((&"fen", fen)) => {
+            let fen = fen.join(" ");
+            let board = board_from_fen(&fen).map_err(|_| format!("invalid FEN {}", fen))?;
+            // `Board` doesn't keep the halfmove clock, so read it ourselves
+            Ok((board, halfmove_clock(&fen)))
+        }
+        _ => Err(format!("invalid position {}", arguments.join(" "))),
+    }
+}
+
+fn format_info(result: &SearchResult) -> String {
+    let nps = result.nodes * 1000 / result.elapsed_ms.max(1);
+    let mut line = format!(
+        "info depth {} seldepth {} score {} nodes {} nps {} hashfull {} time {}",
+        result.depth,
+        result.seldepth,
+        format_score(result.score),
+        result.nodes,
+        nps,
+        result.hashfull,
+        result.elapsed_ms
+    );
+    if !result.pv.is_empty() {
+        line.push_str(" pv");
+        for chess_move in &result.pv {
+            line.push_str(&format!(" {}", chess_move));
+        }
+    }
+    line
+}
+
+fn format_score(score: Score) -> String {
+    match score {
+        Score::Centipawns(centipawns) => format!("cp {}", centipawns),
+        Score::Mate(moves) => format!("mate {}", moves),
+    }
+}
+
+fn send(message: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", message);
+    let _ = stdout.flush();
+}
@@ -1,19 +1,55 @@
+use gloo_events::EventListener;
 use gloo_timers::callback::Timeout;
-use std::collections::HashSet;
-use std::str::FromStr;
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    str::FromStr,
+};
 use wasm_bindgen::JsCast;
 
 use crate::{
-    opening_book::opening_book_move, search::choose_move, wasm::game_over_screen::GameOverScreen,
+    constants::AI_MOVE_TIME_MS,
+    fen::{fullmove_from_move_ply, move_ply_from_fullmove},
+    game_history::GameHistory,
+    opening_book::opening_book_move,
+    pgn::{eval_comment, Pgn},
+    san::{game_to_san, move_to_san},
+    search_limits::SearchLimits,
+    wasm::engine_worker::{spawn_search, PendingSearch, SearchInfo, SearchUpdate},
+    wasm::game_over_screen::GameOverScreen,
+    wasm::move_history::MoveHistory,
+    wasm::pgn_controls::PgnControls,
+    wasm::position_editor::PositionEditor,
+    wasm::promotion_dialog::PromotionDialog,
+    wasm::side_select_screen::SideSelectScreen,
     wasm::square::SquareComp,
 };
-use chess::{Board, BoardStatus, ChessMove, Color, File, Game, MoveGen, Piece, Rank, Square};
-use web_sys::HtmlAudioElement;
+use chess::{
+    Action, Board, BoardStatus, ChessMove, Color, File, Game, MoveGen, Piece, Rank, Square,
+};
+use web_sys::{HtmlAudioElement, KeyboardEvent};
 use yew::prelude::*;
 
+// the player names in saved games
+const HUMAN_NAME: &str = "Human";
+const ENGINE_NAME: &str = "Rust Chess";
+// how much of the line the engine expects to show while it thinks
+const MAX_SHOWN_PV_MOVES: usize = 8;
+
+// the AI's move while it is on the way. dropping either half cancels it
+#[derive(Default)]
+struct PendingAiMove {
+    // the position and ply the move is for, so that rendering again doesn't start over
+    position: Option<(Board, usize)>,
+    // the opening book lookup, delayed a little so that the human's move shows up first
+    _timeout: Option<Timeout>,
+    // the search, once it turns out the book has no move
+    search: Option<PendingSearch>,
+}
+
 fn play_move_sound(board: &Board, chess_move: &ChessMove, is_ai: bool) {
     let is_capture = is_move_a_capture(board, chess_move);
-    let mut board_after_move = board.clone();
+    let mut board_after_move = *board;
     board.make_move(*chess_move, &mut board_after_move);
 
     let is_check = board_after_move.checkers().popcnt() > 0;
@@ -67,67 +103,150 @@ fn is_move_a_capture(board: &Board, chess_move: &ChessMove) -> bool {
                 == 1;
     }
 
-    return false;
+    false
 }
 
 fn is_move_a_castle(board: &Board, chess_move: &ChessMove) -> bool {
     let target_square = chess_move.get_dest();
     let source_square = chess_move.get_source();
-    return board.piece_on(source_square).unwrap() == Piece::King
+    board.piece_on(source_square).unwrap() == Piece::King
         && (source_square.get_file().to_index() as i8 - target_square.get_file().to_index() as i8)
             .abs()
-            > 1;
+            > 1
+}
+
+// the square drawn at the given index of the board, counting left to right from the top left corner.
+// a flipped board is seen from black's side, so it is rotated 180 degrees
+pub(super) fn square_at(index: usize, flipped: bool) -> Square {
+    let (rank, file) = if flipped {
+        (index / 8, 7 - index % 8)
+    } else {
+        (7 - index / 8, index % 8)
+    };
+    Square::make_square(Rank::from_index(rank), File::from_index(file))
+}
+
+// where the picture of a piece is
+pub(super) fn piece_image(piece: Piece, color: Color) -> String {
+    let color_prefix = if color == Color::White { "w" } else { "b" };
+    format!("img/{}{}.svg", color_prefix, piece.to_string(Color::White))
+}
+
+// the moves made in the game, leaving out draw offers and resignations
+fn moves_played(game: &Game) -> Vec<ChessMove> {
+    game.actions()
+        .iter()
+        .filter_map(|action| match action {
+            Action::MakeMove(chess_move) => Some(*chess_move),
+            _ => None,
+        })
+        .collect()
+}
+
+// whether the AI would still be playing from the opening book after these moves, which is the case
+// as long as the book had a move every time it was the AI's turn
+fn still_in_opening_book(start_position: Board, moves: &[ChessMove], ai_color: Color) -> bool {
+    let mut board = start_position;
+    for chess_move in moves {
+        if board.side_to_move() == ai_color && opening_book_move(&board).is_none() {
+            return false;
+        }
+        board = board.make_move_new(*chess_move);
+    }
+    true
+}
+
+// the engine's evaluation and the start of the line it expects, like `+0.35/9 Nf3 d5 c4`
+fn describe_search(board: &Board, info: &SearchInfo) -> String {
+    let mut description = eval_comment(info.score, board.side_to_move(), info.depth);
+    let mut position = *board;
+    for pv_move in info.pv.iter().take(MAX_SHOWN_PV_MOVES) {
+        let Ok(chess_move) = ChessMove::from_str(pv_move) else {
+            break;
+        };
+        description.push(' ');
+        description.push_str(&move_to_san(&position, chess_move));
+        position = position.make_move_new(chess_move);
+    }
+    description
 }
 
-fn parse_board(board: &Board) -> Vec<Option<&str>> {
+fn parse_board(board: &Board, flipped: bool) -> Vec<Option<&str>> {
     let mut result = Vec::new();
 
-    // Split the FEN string by spaces and get the piece placement part
-    for rank in (0..8).rev() {
-        for file in 0..8 {
-            let mut c = '0';
-            let square = Square::make_square(Rank::from_index(rank), File::from_index(file));
-            if board.piece_on(square).is_some() {
-                let p = board.piece_on(square).unwrap();
-                c = p
-                    .to_string(board.color_on(square).unwrap())
-                    .chars()
-                    .next()
-                    .unwrap();
-            }
-            match c {
-                'r' => result.push(Some("img/bR.svg")),
-                'n' => result.push(Some("img/bN.svg")),
-                'b' => result.push(Some("img/bB.svg")),
-                'q' => result.push(Some("img/bQ.svg")),
-                'k' => result.push(Some("img/bK.svg")),
-                'p' => result.push(Some("img/bP.svg")),
-                'R' => result.push(Some("img/wR.svg")),
-                'N' => result.push(Some("img/wN.svg")),
-                'B' => result.push(Some("img/wB.svg")),
-                'Q' => result.push(Some("img/wQ.svg")),
-                'K' => result.push(Some("img/wK.svg")),
-                'P' => result.push(Some("img/wP.svg")),
-                _ => result.push(None),
-            }
+    for index in 0..64 {
+        let mut c = '0';
+        let square = square_at(index, flipped);
+        if board.piece_on(square).is_some() {
+            let p = board.piece_on(square).unwrap();
+            c = p
+                .to_string(board.color_on(square).unwrap())
+                .chars()
+                .next()
+                .unwrap();
+        }
+        match c {
+            'r' => result.push(Some("img/bR.svg")),
+            'n' => result.push(Some("img/bN.svg")),
+            'b' => result.push(Some("img/bB.svg")),
+            'q' => result.push(Some("img/bQ.svg")),
+            'k' => result.push(Some("img/bK.svg")),
+            'p' => result.push(Some("img/bP.svg")),
+            'R' => result.push(Some("img/wR.svg")),
+            'N' => result.push(Some("img/wN.svg")),
+            'B' => result.push(Some("img/wB.svg")),
+            'Q' => result.push(Some("img/wQ.svg")),
+            'K' => result.push(Some("img/wK.svg")),
+            'P' => result.push(Some("img/wP.svg")),
+            _ => result.push(None),
         }
     }
     result
 }
 
+// a start position, its halfmove clock, how many plies into the game it is, and the moves played from it
+type LoadedGame = (Board, u32, u32, Vec<ChessMove>);
+
 #[function_component(BoardComp)]
 pub fn board() -> Html {
-    let game = use_state(|| Game::new());
-    let move_ply = use_state(|| 0);
+    let game = use_state(Game::new);
+    let move_ply = use_state(|| 0u32);
     let selected = use_state(|| None);
     let target = use_state(|| None);
-    let human_is_playing = use_state(|| true); // false for now
+    // false for now
+    let human_is_playing = use_state(|| true);
+    // the side the human plays, or none while they are still choosing
+    let human_color = use_state(|| None::<Color>);
     let start_game = use_state(|| false);
     let from_square = use_state(|| None);
     let to_square = use_state(|| None);
     let in_opening_book = use_state(|| true);
+    // the source and target squares of a promotion waiting on the human to pick a piece
+    let pending_promotion = use_state(|| None::<(Square, Square)>);
+    let promotion_choice = use_state(|| None::<Piece>);
+    // the position the game started from, since `Game` doesn't tell us
+    let start_position = use_state(Board::default);
+    // how many plies were played before the start position, going by the fullmove number of its FEN
+    let start_move_ply = use_state(|| 0u32);
+    // the halfmove clock of the start position, so that the fifty move rule counts the plies before it
+    let start_halfmove_clock = use_state(|| 0u32);
+    // whether the human is setting up a position to play from
+    let editing_position = use_state(|| false);
+    // how many plies into the game the board is showing, or none to follow the game as it is played
+    let viewing_ply = use_state(|| None::<usize>);
+    // the AI's next move while it is being worked out, kept so a takeback can cancel it
+    let ai_move = use_mut_ref(PendingAiMove::default);
+    // what the engine thinks of the position, as of its last search
+    let engine_info = use_state(|| None::<String>);
+    // the AI's evaluation of each of its moves, by ply, which are saved as comments in the PGN
+    let eval_comments = use_state(HashMap::<usize, String>::new);
     let board = game.current_position();
-    let board_copy: Board = board.clone();
+    let moves_played = moves_played(&game);
+    let num_plies = moves_played.len();
+    let history = GameHistory::from_moves(&start_position, *start_halfmove_clock, &moves_played);
+    let fullmove_number = fullmove_from_move_ply(*move_ply);
+    let sans = game_to_san(&start_position, game.actions());
+    let board_copy: Board = board;
     let set_selected = {
         let selected = selected.clone();
         Callback::from(move |new_selected| selected.set(new_selected))
@@ -136,30 +255,277 @@ pub fn board() -> Html {
         let target = target.clone();
         Callback::from(move |new_target| target.set(new_target))
     };
+    let choose_promotion = {
+        let selected = selected.clone();
+        let target = target.clone();
+        let pending_promotion = pending_promotion.clone();
+        let promotion_choice = promotion_choice.clone();
+        Callback::from(move |piece: Option<Piece>| {
+            if let (Some(piece), Some((source, dest))) = (piece, *pending_promotion) {
+                // replay the move, this time knowing which piece to promote to
+                promotion_choice.set(Some(piece));
+                selected.set(Some(source));
+                target.set(Some(dest));
+            }
+            pending_promotion.set(None);
+        })
+    };
+    let choose_side = {
+        let human_color = human_color.clone();
+        Callback::from(move |color| human_color.set(Some(color)))
+    };
+    let view_ply = {
+        let viewing_ply = viewing_ply.clone();
+        Callback::from(move |ply: usize| {
+            // going back to the latest position means following the game again
+            viewing_ply.set(if ply >= num_plies { None } else { Some(ply) })
+        })
+    };
+    {
+        // step through the game with the arrow keys
+        let view_ply = view_ply.clone();
+        let shown_ply = viewing_ply.unwrap_or(num_plies);
+        use_effect_with_deps(
+            move |_| {
+                let window = web_sys::window().expect("no global `window` exists");
+                let listener = EventListener::new(&window, "keydown", move |event| {
+                    let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                        return;
+                    };
+                    match event.key().as_str() {
+                        "ArrowLeft" => view_ply.emit(shown_ply.saturating_sub(1)),
+                        "ArrowRight" => view_ply.emit(shown_ply + 1),
+                        _ => {}
+                    }
+                });
+                move || drop(listener)
+            },
+            (shown_ply, num_plies),
+        );
+    }
     let reset_game = {
         let game = game.clone();
+        let move_ply = move_ply.clone();
         let in_opening_book = in_opening_book.clone();
         let from_square = from_square.clone();
         let to_square = to_square.clone();
-        Callback::from(move |new_game| {
+        let human_color = human_color.clone();
+        let start_position = start_position.clone();
+        let start_move_ply = start_move_ply.clone();
+        let start_halfmove_clock = start_halfmove_clock.clone();
+        let viewing_ply = viewing_ply.clone();
+        let eval_comments = eval_comments.clone();
+        let ai_move = ai_move.clone();
+        let engine_info = engine_info.clone();
+        Callback::from(move |new_game: Game| {
+            *ai_move.borrow_mut() = PendingAiMove::default();
+            engine_info.set(None);
+            start_position.set(new_game.current_position());
+            start_move_ply.set(0);
+            start_halfmove_clock.set(0);
+            viewing_ply.set(None);
+            eval_comments.set(HashMap::new());
             game.set(new_game);
+            move_ply.set(0);
             in_opening_book.set(true);
             from_square.set(None);
             to_square.set(None);
+            // let the human pick a side again
+            human_color.set(None);
+        })
+    };
+    let human_to_move = *human_is_playing && *human_color == Some(board.side_to_move());
+    // switches to the game reached by playing `moves` from a start position that is `start_ply`
+    // plies into the game and has the given halfmove clock, like a takeback or loading a game does
+    let load_moves = {
+        let game = game.clone();
+        let start_position = start_position.clone();
+        let start_move_ply = start_move_ply.clone();
+        let start_halfmove_clock = start_halfmove_clock.clone();
+        let move_ply = move_ply.clone();
+        let in_opening_book = in_opening_book.clone();
+        let from_square = from_square.clone();
+        let to_square = to_square.clone();
+        let selected = selected.clone();
+        let target = target.clone();
+        let pending_promotion = pending_promotion.clone();
+        let promotion_choice = promotion_choice.clone();
+        let viewing_ply = viewing_ply.clone();
+        let ai_move = ai_move.clone();
+        let engine_info = engine_info.clone();
+        let human_color = *human_color;
+        Callback::from(
+            move |(new_start_position, halfmove_clock, start_ply, moves): LoadedGame| {
+                // this stops the AI from playing a move in the old position
+                *ai_move.borrow_mut() = PendingAiMove::default();
+                engine_info.set(None);
+
+                let mut new_game = Game::new_with_board(new_start_position);
+                for chess_move in &moves {
+                    new_game.make_move(*chess_move);
+                }
+                game.set(new_game);
+                start_position.set(new_start_position);
+                start_move_ply.set(start_ply);
+                start_halfmove_clock.set(halfmove_clock);
+                move_ply.set(start_ply + moves.len() as u32);
+                // until the human picks a side we don't know which moves were the AI's
+                in_opening_book.set(human_color.is_none_or(|human_color| {
+                    still_in_opening_book(new_start_position, &moves, !human_color)
+                }));
+                from_square.set(moves.last().map(|chess_move| chess_move.get_source()));
+                to_square.set(moves.last().map(|chess_move| chess_move.get_dest()));
+                selected.set(None);
+                target.set(None);
+                pending_promotion.set(None);
+                promotion_choice.set(None);
+                viewing_ply.set(None);
+            },
+        )
+    };
+    // take back the AI's reply along with the human's move, or just the human's move if the AI
+    // hasn't replied yet
+    let takeback_plies = if human_to_move { 2 } else { 1 };
+    let can_take_back = *human_is_playing && human_color.is_some() && num_plies >= takeback_plies;
+    let take_back = {
+        let load_moves = load_moves.clone();
+        let eval_comments = eval_comments.clone();
+        let start_position = *start_position;
+        let start_ply = *start_move_ply;
+        let halfmove_clock = *start_halfmove_clock;
+        let moves_played = moves_played.clone();
+        Callback::from(move |_| {
+            if moves_played.len() < takeback_plies {
+                return;
+            }
+            let moves_kept = moves_played[..moves_played.len() - takeback_plies].to_vec();
+            let mut comments = (*eval_comments).clone();
+            comments.retain(|ply, _| *ply < moves_kept.len());
+            eval_comments.set(comments);
+            load_moves.emit((start_position, halfmove_clock, start_ply, moves_kept));
         })
     };
+    let pgn = {
+        let mut pgn = Pgn::from_game(
+            &game,
+            *start_position,
+            *start_halfmove_clock,
+            fullmove_from_move_ply(*start_move_ply),
+        );
+        for (ply, comment) in eval_comments.iter() {
+            if let Some(pgn_move) = pgn.moves.get_mut(*ply) {
+                pgn_move.comment = Some(comment.clone());
+            }
+        }
+        if let Some(human_color) = *human_color {
+            let (white, black) = if human_color == Color::White {
+                (HUMAN_NAME, ENGINE_NAME)
+            } else {
+                (ENGINE_NAME, HUMAN_NAME)
+            };
+            pgn.set_tag("White", white);
+            pgn.set_tag("Black", black);
+        }
+        pgn
+    };
+    let load_pgn = {
+        let load_moves = load_moves.clone();
+        let eval_comments = eval_comments.clone();
+        Callback::from(move |pgn: Pgn| {
+            // keep the comments so saving the game again doesn't lose them
+            eval_comments.set(
+                pgn.moves
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(ply, pgn_move)| Some((ply, pgn_move.comment.clone()?)))
+                    .collect(),
+            );
+            let moves = pgn
+                .moves
+                .iter()
+                .map(|pgn_move| pgn_move.chess_move)
+                .collect();
+            let start_ply =
+                move_ply_from_fullmove(pgn.first_move_number(), pgn.start_position.side_to_move());
+            load_moves.emit((
+                pgn.start_position,
+                pgn.start_halfmove_clock(),
+                start_ply,
+                moves,
+            ));
+        })
+    };
+    let set_up_position = {
+        let editing_position = editing_position.clone();
+        let ai_move = ai_move.clone();
+        Callback::from(move |_| {
+            *ai_move.borrow_mut() = PendingAiMove::default();
+            editing_position.set(true);
+        })
+    };
+    let play_from_position = {
+        let load_moves = load_moves.clone();
+        let editing_position = editing_position.clone();
+        let eval_comments = eval_comments.clone();
+        let human_color = human_color.clone();
+        Callback::from(
+            move |(position, halfmove_clock, fullmove_number): (Board, u32, u32)| {
+                editing_position.set(false);
+                eval_comments.set(HashMap::new());
+                // the human might want to play the other side of the new position
+                human_color.set(None);
+                let start_ply = move_ply_from_fullmove(fullmove_number, position.side_to_move());
+                load_moves.emit((position, halfmove_clock, start_ply, Vec::new()));
+            },
+        )
+    };
+    let cancel_set_up = {
+        let editing_position = editing_position.clone();
+        Callback::from(move |_| editing_position.set(false))
+    };
+    // an earlier position can be looked at, but not played from
+    let shown_board = match *viewing_ply {
+        Some(ply) => moves_played[..ply]
+            .iter()
+            .fold(*start_position, |position, chess_move| {
+                position.make_move_new(*chess_move)
+            }),
+        None => board,
+    };
+    let can_move = human_to_move && viewing_ply.is_none();
+    // highlight the move that led to the shown position
+    let (highlighted_from, highlighted_to) = match *viewing_ply {
+        Some(0) => (None, None),
+        Some(ply) => (
+            Some(moves_played[ply - 1].get_source()),
+            Some(moves_played[ply - 1].get_dest()),
+        ),
+        None => (*from_square, *to_square),
+    };
+    // when playing black, look at the board from black's side
+    let flipped = *human_is_playing && *human_color == Some(Color::Black);
     let mut game_clone = (*game).clone();
     let check_game_ended = (*game).clone();
-    if !(*human_is_playing) && !(*start_game) {
+    if *editing_position {
+        // then wait for the human to finish setting up the position
+    } else if !(*human_is_playing) && !(*start_game) {
         // then just wait for human to start the game between AIs
-    } else if (*target).is_some() && (*selected).is_some() && *human_is_playing {
-        let mut new_move = ChessMove::new(selected.unwrap(), target.unwrap(), None);
-        if target.unwrap().get_rank() == Rank::Eighth
-            && board.piece_on(selected.unwrap()).unwrap() == Piece::Pawn
-        {
-            // then we are promoting a pawn. lets just auto queen for now
-            new_move = ChessMove::new(selected.unwrap(), target.unwrap(), Some(Piece::Queen));
-        }
+    } else if *human_is_playing && human_color.is_none() {
+        // then just wait for the human to pick a side
+    } else if (*target).is_some()
+        && (*selected).is_some()
+        && human_to_move
+        && target.unwrap().get_rank() == board.side_to_move().to_their_backrank()
+        && board.piece_on(selected.unwrap()).unwrap() == Piece::Pawn
+        && promotion_choice.is_none()
+    {
+        // then we are promoting a pawn, so ask the human what it should become before making the move
+        pending_promotion.set(Some((selected.unwrap(), target.unwrap())));
+        selected.set(None);
+        target.set(None);
+    } else if (*target).is_some() && (*selected).is_some() && human_to_move {
+        let new_move = ChessMove::new(selected.unwrap(), target.unwrap(), *promotion_choice);
+        promotion_choice.set(None);
         play_move_sound(&board_copy, &new_move, false);
         // game.set(Game::new_with_board(board_copy));
         game_clone.make_move(new_move);
@@ -174,56 +540,84 @@ pub fn board() -> Html {
         to_square.set(*target);
         selected.set(None);
         target.set(None);
-    } else if (board.side_to_move() == Color::Black && game.result().is_none())
-        || (board.side_to_move() == Color::White && !(*human_is_playing) && game.result().is_none())
+    } else if game.result().is_none()
+        && !human_to_move
+        && ai_move.borrow().position != Some((board, num_plies))
     {
-        let from_square_cloned = from_square.clone();
-        let to_square_cloned = to_square.clone();
-        let timeout = Timeout::new(5, move || {
-            if *in_opening_book {
-                let ai_move = opening_book_move(board.get_hash());
-                if ai_move.is_some() {
-                    let ai_move = ai_move.unwrap();
-                    play_move_sound(&board_copy, &ai_move, true);
-                    game_clone.make_move(ai_move);
-                    from_square_cloned.set(Some(ai_move.get_source()));
-                    to_square_cloned.set(Some(ai_move.get_dest()));
-                } else {
-                    // we just got out of opening book, so choose a move on our own now
-                    in_opening_book.set(false);
-                    let ai_move = choose_move(&board, *move_ply);
-                    if ai_move.is_some() {
-                        let ai_move = ai_move.unwrap();
-                        play_move_sound(&board_copy, &ai_move, true);
-                        game_clone.make_move(ai_move);
-                        from_square_cloned.set(Some(ai_move.get_source()));
-                        to_square_cloned.set(Some(ai_move.get_dest()));
-                    }
+        let play_ai_move = {
+            let game = game.clone();
+            let move_ply = move_ply.clone();
+            let from_square = from_square.clone();
+            let to_square = to_square.clone();
+            Rc::new(move |chess_move: ChessMove| {
+                let mut game_clone = game_clone.clone();
+                play_move_sound(&board_copy, &chess_move, true);
+                game_clone.make_move(chess_move);
+                if game_clone.can_declare_draw() {
+                    game_clone.declare_draw();
                 }
-            } else {
-                let ai_move = choose_move(&board, *move_ply);
-                if ai_move.is_some() {
-                    let ai_move = ai_move.unwrap();
-                    play_move_sound(&board_copy, &ai_move, true);
-                    game_clone.make_move(ai_move);
-                    from_square_cloned.set(Some(ai_move.get_source()));
-                    to_square_cloned.set(Some(ai_move.get_dest()));
+                from_square.set(Some(chess_move.get_source()));
+                to_square.set(Some(chess_move.get_dest()));
+                game.set(game_clone);
+                move_ply.set(*move_ply + 1);
+            })
+        };
+        let on_update = {
+            let play_ai_move = play_ai_move.clone();
+            let eval_comments = eval_comments.clone();
+            let engine_info = engine_info.clone();
+            let ai_move = ai_move.clone();
+            move |update: SearchUpdate| match update {
+                SearchUpdate::Progress(info) => {
+                    engine_info.set(Some(describe_search(&board, &info)));
+                }
+                SearchUpdate::Done(info) => {
+                    // the search is over, so there is nothing left to cancel
+                    ai_move.borrow_mut().search.take();
+                    engine_info.set(Some(describe_search(&board, &info)));
+                    let mut comments = (*eval_comments).clone();
+                    comments.insert(
+                        num_plies,
+                        eval_comment(info.score, board.side_to_move(), info.depth),
+                    );
+                    eval_comments.set(comments);
+                    if let Some(best_move) = info.best_move() {
+                        play_ai_move(best_move);
+                    }
                 }
             }
-            if game_clone.can_declare_draw() {
-                game_clone.declare_draw();
+        };
+        let pending_ai_move = ai_move.clone();
+        let history = history.clone();
+        let timeout = Timeout::new(5, move || {
+            let book_move = if *in_opening_book {
+                opening_book_move(&board)
+            } else {
+                None
+            };
+            if let Some(book_move) = book_move {
+                play_ai_move(book_move);
+            } else {
+                // we are out of the opening book, so choose a move on our own now
+                in_opening_book.set(false);
+                let limits = SearchLimits::move_time(AI_MOVE_TIME_MS);
+                let search = spawn_search(&board, history, limits, on_update);
+                pending_ai_move.borrow_mut().search = Some(search);
             }
-            game.set(game_clone);
-            move_ply.set(*move_ply + 1);
         });
-        timeout.forget();
+        // replacing an earlier move cancels it, so the AI only ever has one move on the way
+        *ai_move.borrow_mut() = PendingAiMove {
+            position: Some((board, num_plies)),
+            _timeout: Some(timeout),
+            search: None,
+        };
     }
 
-    let board_vec = parse_board(&board_copy);
+    let board_vec = parse_board(&shown_board, flipped);
 
     // add move circles
     let mut moves = HashSet::new();
-    if selected.is_some() {
+    if selected.is_some() && can_move {
         let square: Square = selected.unwrap();
         for legal_move in MoveGen::new_legal(&board_copy) {
             if legal_move.get_source() == square {
@@ -234,7 +628,15 @@ pub fn board() -> Html {
     // for checking if game has ended
     // let game_after_move = Game::new_with_board(board_copy);
 
+    if *editing_position {
+        return html! {
+            <PositionEditor board={board_copy} halfmove_clock={history.halfmove_clock()} fullmove_number={fullmove_number}
+                start_position={play_from_position} cancel={cancel_set_up}/>
+        };
+    }
+
     html! {
+        <div class="game-container">
         <div
             class="board"
         >
@@ -244,17 +646,10 @@ pub fn board() -> Html {
             let piece_prop = piece.map(|p| p.to_string()); // Convert Option<&str> to Option<String>
 
             // get the square
-            let file_index = index % 8;
-            let rank_index = 7 - (index / 8);
-            let algebraic = format!(
-                "{}{}",
-                (file_index + 'a' as usize) as u8 as char,
-                (rank_index + '1' as usize) as u8 as char
-            );
-            let square = Square::from_str(&algebraic).unwrap();
+            let square = square_at(index, flipped);
             let can_move_to = moves.contains(&square);
-            let source_square = from_square.is_some() && from_square.unwrap() == square;
-            let dest_square = to_square.is_some() && to_square.unwrap() == square;
+            let source_square = highlighted_from == Some(square);
+            let dest_square = highlighted_to == Some(square);
 
             match piece {
                 Some(_) => html!{<SquareComp color={color} piece={piece_prop} set_selected={set_selected.clone()} set_target={set_target.clone()} can_move_to={can_move_to} square={square} source_square={source_square} dest_square={dest_square}/>},
@@ -268,7 +663,24 @@ pub fn board() -> Html {
             else if !(*human_is_playing) && !(*start_game) {
                 <button onclick={Callback::from(move |_| start_game.set(true))}>{"Start Game"}</button>
             }
+            else if *human_is_playing && human_color.is_none() {
+                <SideSelectScreen choose_side={choose_side}/>
+            }
+            else if pending_promotion.is_some() {
+                <PromotionDialog color={board_copy.side_to_move()} choose_promotion={choose_promotion}/>
+            }
         }}
         </div>
+        <div class="side-panel">
+            <MoveHistory sans={sans} first_to_move={start_position.side_to_move()}
+                first_move_number={fullmove_from_move_ply(*start_move_ply)} shown_ply={viewing_ply.unwrap_or(num_plies)} view_ply={view_ply}/>
+            if let Some(engine_info) = &*engine_info {
+                <div class="engine-info">{engine_info}</div>
+            }
+            <button class="game-restart-button" onclick={take_back} disabled={!can_take_back}>{"Take back"}</button>
+            <PgnControls pgn={pgn} load_pgn={load_pgn}/>
+            <button class="game-restart-button cancel-button" onclick={set_up_position}>{"Set up position"}</button>
+        </div>
+        </div>
     }
 }
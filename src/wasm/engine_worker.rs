@@ -0,0 +1,300 @@
+use std::{cell::RefCell, rc::Rc, str::FromStr, sync::atomic::AtomicBool};
+
+use chess::{Board, ChessMove};
+use gloo_worker::{Bincode, Codec};
+use js_sys::Array;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{Blob, BlobPropertyBag, DedicatedWorkerGlobalScope, MessageEvent, Url, Worker};
+
+use crate::{
+    constants::DEFAULT_HASH_MB,
+    game_history::GameHistory,
+    search::{iterative_deepening, Score, SearchOptions, SearchResult},
+    search_limits::{SearchLimits, SystemClock},
+    transposition_table::TranspositionTable,
+};
+
+// where trunk puts the javascript that loads the worker's wasm, next to the page's own
+const WORKER_PATH: &str = "engine_worker.js";
+
+/// What the page asks the engine worker to do.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub fen: String,
+    pub history: GameHistory,
+    pub limits: SearchLimits,
+}
+
+/// What the engine worker sends back while it searches.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SearchUpdate {
+    /// sent after every completed depth
+    Progress(SearchInfo),
+    /// the last message of a search, with the move to play
+    Done(SearchInfo),
+}
+
+// everything the worker sends to the page
+#[derive(Serialize, Deserialize)]
+enum FromWorker {
+    // the worker's wasm has loaded, so it can take requests now. anything sent before this is lost
+    Loaded,
+    Update(SearchUpdate),
+}
+
+/// A `SearchResult` that can be sent between threads, with the moves in UCI notation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchInfo {
+    pub best_move: Option<String>,
+    pub score: Score,
+    pub depth: u8,
+    pub nodes: u64,
+    pub elapsed_ms: u64,
+    pub pv: Vec<String>,
+}
+
+impl SearchInfo {
+    fn from_result(result: &SearchResult) -> SearchInfo {
+        SearchInfo {
+            best_move: result.best_move.map(|chess_move| chess_move.to_string()),
+            score: result.score,
+            depth: result.depth,
+            nodes: result.nodes,
+            elapsed_ms: result.elapsed_ms,
+            pv: result.pv.iter().map(ChessMove::to_string).collect(),
+        }
+    }
+
+    pub fn best_move(&self) -> Option<ChessMove> {
+        ChessMove::from_str(self.best_move.as_ref()?).ok()
+    }
+}
+
+/// Runs the engine's side of the worker: every request is searched with the same transposition table, so
+/// what one move of the game found helps with the next. This is all the worker's `main` has to call.
+pub fn run_engine_worker() {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let respond = {
+        let scope = scope.clone();
+        move |message: FromWorker| {
+            let _ = scope.post_message(&Bincode::encode(message));
+        }
+    };
+    let mut transposition_table = TranspositionTable::new(DEFAULT_HASH_MB);
+    let on_message = {
+        let respond = respond.clone();
+        Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let request: SearchRequest = Bincode::decode(event.data());
+            let result = search(&request, &mut transposition_table, |result| {
+                respond(FromWorker::Update(SearchUpdate::Progress(
+                    SearchInfo::from_result(result),
+                )))
+            });
+            respond(FromWorker::Update(SearchUpdate::Done(result)));
+        })
+    };
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    // the worker lives as long as the page wants it to, and so does its handler
+    on_message.forget();
+    respond(FromWorker::Loaded);
+}
+
+fn search(
+    request: &SearchRequest,
+    transposition_table: &mut TranspositionTable,
+    on_iteration: impl FnMut(&SearchResult),
+) -> SearchInfo {
+    let Ok(board) = Board::from_str(&request.fen) else {
+        return SearchInfo {
+            best_move: None,
+            score: Score::Centipawns(0),
+            depth: 0,
+            nodes: 0,
+            elapsed_ms: 0,
+            pv: Vec::new(),
+        };
+    };
+    // nothing can set this: the worker can't read messages until the search returns, so the page stops a
+    // search by terminating the worker instead
+    let stop = AtomicBool::new(false);
+    let result = iterative_deepening(
+        &board,
+        &request.history,
+        &request.limits,
+        &SearchOptions::default(),
+        transposition_table,
+        &SystemClock,
+        &stop,
+        on_iteration,
+    );
+    SearchInfo::from_result(&result)
+}
+
+// the page's end of the worker
+struct Connection {
+    // requests waiting for the worker's wasm to load, or none once it has
+    queued: Option<Vec<SearchRequest>>,
+    // where the updates of the search in progress go, or none when the worker is idle
+    on_update: Option<Rc<dyn Fn(SearchUpdate)>>,
+}
+
+// a worker that outlives any one search, so that its transposition table does too
+struct EngineWorker {
+    worker: Worker,
+    connection: Rc<RefCell<Connection>>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+thread_local! {
+    static ENGINE_WORKER: RefCell<Option<Rc<EngineWorker>>> = const { RefCell::new(None) };
+}
+
+impl EngineWorker {
+    // the running worker, or a new one if there isn't one
+    fn get() -> Rc<EngineWorker> {
+        ENGINE_WORKER.with(|engine_worker| {
+            engine_worker
+                .borrow_mut()
+                .get_or_insert_with(|| Rc::new(EngineWorker::spawn()))
+                .clone()
+        })
+    }
+
+    fn spawn() -> EngineWorker {
+        let worker = create_worker(WORKER_PATH);
+        let connection = Rc::new(RefCell::new(Connection {
+            queued: Some(Vec::new()),
+            on_update: None,
+        }));
+        let on_message = {
+            let worker = worker.clone();
+            let connection = connection.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                match Bincode::decode(event.data()) {
+                    FromWorker::Loaded => {
+                        let queued = connection.borrow_mut().queued.take();
+                        for request in queued.into_iter().flatten() {
+                            let _ = worker.post_message(&Bincode::encode(request));
+                        }
+                    }
+                    FromWorker::Update(update) => {
+                        // let go of the connection first, since the update can start or cancel a search
+                        let on_update = if matches!(update, SearchUpdate::Done(_)) {
+                            connection.borrow_mut().on_update.take()
+                        } else {
+                            connection.borrow().on_update.clone()
+                        };
+                        if let Some(on_update) = on_update {
+                            on_update(update);
+                        }
+                    }
+                }
+            })
+        };
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        EngineWorker {
+            worker,
+            connection,
+            _on_message: on_message,
+        }
+    }
+
+    fn is_searching(&self) -> bool {
+        self.connection.borrow().on_update.is_some()
+    }
+
+    fn send(&self, request: SearchRequest, on_update: Rc<dyn Fn(SearchUpdate)>) {
+        let mut connection = self.connection.borrow_mut();
+        connection.on_update = Some(on_update);
+        match connection.queued.as_mut() {
+            Some(queued) => queued.push(request),
+            None => {
+                let _ = self.worker.post_message(&Bincode::encode(request));
+            }
+        }
+    }
+
+    // the worker can't read messages while it searches, so the only way to stop it is to shut it down. the
+    // next search gets a new worker, with an empty transposition table
+    fn terminate(self: &Rc<Self>) {
+        self.worker.set_onmessage(None);
+        self.worker.terminate();
+        self.connection.borrow_mut().on_update = None;
+        ENGINE_WORKER.with(|engine_worker| {
+            let mut engine_worker = engine_worker.borrow_mut();
+            if engine_worker
+                .as_ref()
+                .is_some_and(|running| Rc::ptr_eq(running, self))
+            {
+                *engine_worker = None;
+            }
+        });
+    }
+}
+
+// starts a worker the same way trunk's own loader would, by importing the worker's javascript and then
+// loading its wasm
+fn create_worker(path: &str) -> Worker {
+    let href = web_sys::window()
+        .and_then(|window| window.location().href().ok())
+        .expect("the page has no location");
+    let script_url = Url::new_with_base(path, &href)
+        .expect("the worker's path isn't a valid url")
+        .to_string();
+    let wasm_url = script_url.replace(".js", "_bg.wasm");
+    let loader = Array::of1(
+        &format!(
+            r#"importScripts("{}");wasm_bindgen("{}");"#,
+            script_url, wasm_url
+        )
+        .into(),
+    );
+    let options = BlobPropertyBag::new();
+    options.set_type("application/javascript");
+    let blob = Blob::new_with_str_sequence_and_options(&loader, &options)
+        .expect("the worker's loader couldn't be made");
+    let url = Url::create_object_url_with_blob(&blob).expect("the worker's loader has no url");
+    Worker::new(&url).expect("the worker couldn't be started")
+}
+
+/// A search running in the engine worker. Dropping it before the search is done cancels it, and
+/// `on_update` is never called again.
+pub struct PendingSearch {
+    engine_worker: Rc<EngineWorker>,
+}
+
+impl Drop for PendingSearch {
+    fn drop(&mut self) {
+        if self.engine_worker.is_searching() {
+            self.engine_worker.terminate();
+        }
+    }
+}
+
+/// Starts searching `board`, which `history` led up to, in the engine worker, calling `on_update` with its
+/// progress and its move. The worker is kept from one search to the next, so that it doesn't have to start
+/// over every move, unless a search is cancelled. Then it has to be shut down, since it can't be told to
+/// stop.
+pub fn spawn_search(
+    board: &Board,
+    history: GameHistory,
+    limits: SearchLimits,
+    on_update: impl Fn(SearchUpdate) + 'static,
+) -> PendingSearch {
+    let mut engine_worker = EngineWorker::get();
+    if engine_worker.is_searching() {
+        // only one search can run at a time, so the old one has to go
+        engine_worker.terminate();
+        engine_worker = EngineWorker::get();
+    }
+    engine_worker.send(
+        SearchRequest {
+            fen: board.to_string(),
+            history,
+            limits,
+        },
+        Rc::new(on_update),
+    );
+    PendingSearch { engine_worker }
+}
@@ -1,4 +1,10 @@
 pub mod app;
 pub mod board;
+pub mod engine_worker;
 mod game_over_screen;
+mod move_history;
+mod pgn_controls;
+mod position_editor;
+mod promotion_dialog;
+mod side_select_screen;
 mod square;
@@ -0,0 +1,67 @@
+use chess::Color;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct MoveHistoryProps {
+    /// every move of the game so far in SAN
+    pub sans: Vec<String>,
+    /// the side that made the first move, since a game set up from a position can start with black
+    pub first_to_move: Color,
+    /// the fullmove number of the first move
+    pub first_move_number: u32,
+    /// how many plies into the game the shown position is
+    pub shown_ply: usize,
+    /// emits the number of plies into the game the board should show
+    pub view_ply: Callback<usize>,
+}
+
+#[function_component(MoveHistory)]
+pub fn move_history(props: &MoveHistoryProps) -> Html {
+    // when black moves first, the first row only has black's move in it
+    let offset = if props.first_to_move == Color::Black {
+        1
+    } else {
+        0
+    };
+    let num_rows = (props.sans.len() + offset).div_ceil(2);
+
+    let move_cell = |index: Option<usize>| match index
+        .and_then(|index| props.sans.get(index).map(|san| (index, san)))
+    {
+        Some((index, san)) => {
+            let view_ply = props.view_ply.clone();
+            // the position after this move has been made
+            let ply = index + 1;
+            let class = if ply == props.shown_ply {
+                "move-san shown-move"
+            } else {
+                "move-san"
+            };
+            html! {
+                <span class={class} onclick={Callback::from(move |_| view_ply.emit(ply))}>{san}</span>
+            }
+        }
+        None => html! { <span class="move-san">{"..."}</span> },
+    };
+
+    html! {
+        <div class="move-history">
+            <p class="move-history-title">{"Moves"}</p>
+            <div class="move-list">
+                { for (0..num_rows).map(|row| {
+                    let white_index = (2 * row).checked_sub(offset);
+                    let black_index = 2 * row + 1 - offset;
+                    html! {
+                        <div class="move-row">
+                            <span class="move-number">{format!("{}.", props.first_move_number as usize + row)}</span>
+                            {move_cell(white_index)}
+                            if black_index < props.sans.len() {
+                                {move_cell(Some(black_index))}
+                            }
+                        </div>
+                    }
+                }) }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,104 @@
+use gloo_file::{callbacks::FileReader, Blob, File, ObjectUrl};
+use gloo_timers::callback::Timeout;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlAnchorElement, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::pgn::Pgn;
+
+const PGN_MIME_TYPE: &str = "application/x-chess-pgn";
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct PgnControlsProps {
+    /// the game being played, ready to be saved
+    pub pgn: Pgn,
+    /// emits a game the human loaded from a file
+    pub load_pgn: Callback<Pgn>,
+}
+
+// today's date the way the PGN Date tag wants it
+fn pgn_date() -> String {
+    let now = js_sys::Date::new_0();
+    format!(
+        "{:04}.{:02}.{:02}",
+        now.get_full_year(),
+        now.get_month() + 1,
+        now.get_date()
+    )
+}
+
+fn download(file_name: &str, text: &str) {
+    let url = ObjectUrl::from(Blob::new_with_options(text, Some(PGN_MIME_TYPE)));
+    let document = web_sys::window()
+        .expect("no global `window` exists")
+        .document()
+        .expect("should have a document on window");
+    let link: HtmlAnchorElement = document
+        .create_element("a")
+        .expect("should be able to create a link")
+        .dyn_into()
+        .expect("element should be a link");
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    // revoking the url right away can cancel the download, so give the browser a moment
+    Timeout::new(1000, move || drop(url)).forget();
+}
+
+#[function_component(PgnControls)]
+pub fn pgn_controls(props: &PgnControlsProps) -> Html {
+    // the file being read, which has to be kept around until it is done
+    let file_reader = use_mut_ref(|| None::<FileReader>);
+    let error = use_state(|| None::<String>);
+
+    let save_click_handler = {
+        let pgn = props.pgn.clone();
+        Callback::from(move |_| {
+            let mut pgn = pgn.clone();
+            pgn.set_tag("Date", pgn_date());
+            download("game.pgn", &pgn.to_string());
+        })
+    };
+    let load_change_handler = {
+        let load_pgn = props.load_pgn.clone();
+        let error = error.clone();
+        Callback::from(move |event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            // let the same file be picked again later
+            input.set_value("");
+            let load_pgn = load_pgn.clone();
+            let error = error.clone();
+            let reader = gloo_file::callbacks::read_as_text(&File::from(file), move |text| {
+                let pgn = text
+                    .map_err(|read_error| read_error.to_string())
+                    .and_then(|text| Pgn::parse(&text).map_err(|pgn_error| pgn_error.to_string()));
+                match pgn {
+                    Ok(pgn) => {
+                        error.set(None);
+                        load_pgn.emit(pgn);
+                    }
+                    Err(message) => error.set(Some(format!("Couldn't load the game: {}", message))),
+                }
+            });
+            *file_reader.borrow_mut() = Some(reader);
+        })
+    };
+
+    html! {
+        <div class="pgn-controls">
+            <div class="button-div">
+                <button class="game-restart-button" onclick={save_click_handler}>{"Save PGN"}</button>
+                <label class="game-restart-button">
+                    {"Load PGN"}
+                    <input type="file" accept=".pgn" class="file-input" onchange={load_change_handler}/>
+                </label>
+            </div>
+            if let Some(message) = &*error {
+                <p class="pgn-error">{message}</p>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,451 @@
+use std::{cell::RefCell, rc::Rc, str::FromStr};
+
+use chess::{Board, Color, File, Piece, Rank, Square};
+use web_sys::{DragEvent, HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::{
+    fen::{fullmove_number, halfmove_clock},
+    wasm::board::{piece_image, square_at},
+};
+
+const PALETTE_PIECES: [Piece; 6] = [
+    Piece::King,
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+];
+
+// white kingside, white queenside, black kingside, black queenside
+const CASTLING_OPTIONS: [(&str, char); 4] = [
+    ("White O-O", 'K'),
+    ("White O-O-O", 'Q'),
+    ("Black O-O", 'k'),
+    ("Black O-O-O", 'q'),
+];
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct PositionEditorProps {
+    /// the position the editor starts out with
+    pub board: Board,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    /// emits the position to play from along with its halfmove clock and fullmove number
+    pub start_position: Callback<(Board, u32, u32)>,
+    pub cancel: Callback<()>,
+}
+
+// a position being edited, which doesn't have to be legal until the human wants to play from it
+#[derive(Clone, PartialEq)]
+struct EditorPosition {
+    pieces: [Option<(Piece, Color)>; 64],
+    side_to_move: Color,
+    castling: [bool; 4],
+    // the file of a pawn that just moved two squares
+    en_passant: Option<File>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl EditorPosition {
+    fn from_board(board: &Board, halfmove_clock: u32, fullmove_number: u32) -> Self {
+        let mut pieces = [None; 64];
+        for square in *board.combined() {
+            pieces[square.to_index()] = board.piece_on(square).zip(board.color_on(square));
+        }
+        let white_castling = board.castle_rights(Color::White);
+        let black_castling = board.castle_rights(Color::Black);
+        Self {
+            pieces,
+            side_to_move: board.side_to_move(),
+            castling: [
+                white_castling.has_kingside(),
+                white_castling.has_queenside(),
+                black_castling.has_kingside(),
+                black_castling.has_queenside(),
+            ],
+            en_passant: board.en_passant().map(|square| square.get_file()),
+            halfmove_clock,
+            fullmove_number,
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            pieces: [None; 64],
+            side_to_move: Color::White,
+            castling: [false; 4],
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+            for file in 0..8 {
+                match self.pieces[rank * 8 + file] {
+                    Some((piece, color)) => {
+                        if empty_squares > 0 {
+                            fen.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        fen.push_str(&piece.to_string(color));
+                    }
+                    None => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        let side_to_move = if self.side_to_move == Color::White {
+            "w"
+        } else {
+            "b"
+        };
+        let castling: String = CASTLING_OPTIONS
+            .iter()
+            .zip(self.castling)
+            .filter(|(_, allowed)| *allowed)
+            .map(|((_, letter), _)| *letter)
+            .collect();
+        let castling = if castling.is_empty() {
+            "-".to_string()
+        } else {
+            castling
+        };
+        // the square the pawn skipped over
+        let en_passant = match self.en_passant {
+            Some(file) => {
+                let rank = if self.side_to_move == Color::White {
+                    Rank::Sixth
+                } else {
+                    Rank::Third
+                };
+                Square::make_square(rank, file).to_string()
+            }
+            None => "-".to_string(),
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            fen, side_to_move, castling, en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+
+    // the position as a board, or why it can't be played from
+    fn validate(&self) -> Result<Board, String> {
+        for color in [Color::White, Color::Black] {
+            let num_kings = self
+                .pieces
+                .iter()
+                .filter(|piece| **piece == Some((Piece::King, color)))
+                .count();
+            if num_kings != 1 {
+                return Err("Each side needs exactly one king".to_string());
+            }
+        }
+        let pawn_on_back_rank = (0..8).any(|file| {
+            [file, 56 + file]
+                .iter()
+                .any(|&index| matches!(self.pieces[index], Some((Piece::Pawn, _))))
+        });
+        if pawn_on_back_rank {
+            return Err("Pawns can't be on the first or last rank".to_string());
+        }
+        Board::from_str(&self.to_fen()).map_err(|_| {
+            "The side that isn't moving can't be in check, castling needs the king and rook on \
+             their starting squares and en passant needs a pawn that just moved two squares"
+                .to_string()
+        })
+    }
+}
+
+// makes a function that changes the position, which also throws away any half typed FEN
+fn edit_position(
+    position: &UseStateHandle<EditorPosition>,
+    fen_input: &UseStateHandle<Option<String>>,
+    error: &UseStateHandle<Option<String>>,
+    change: impl Fn(&mut EditorPosition, String) + 'static,
+) -> impl Fn(String) {
+    let position = position.clone();
+    let fen_input = fen_input.clone();
+    let error = error.clone();
+    move |value: String| {
+        let mut new_position = (*position).clone();
+        change(&mut new_position, value);
+        position.set(new_position);
+        fen_input.set(None);
+        error.set(None);
+    }
+}
+
+// a piece being dragged, and the square it is being dragged off of unless it came from the palette
+type DraggedPiece = (Piece, Color, Option<Square>);
+
+#[function_component(PositionEditor)]
+pub fn position_editor(props: &PositionEditorProps) -> Html {
+    let position = {
+        let board = props.board;
+        let (halfmove_clock, fullmove_number) = (props.halfmove_clock, props.fullmove_number);
+        use_state(move || EditorPosition::from_board(&board, halfmove_clock, fullmove_number))
+    };
+    // what clicking a square puts there, where none removes the piece instead
+    let brush = use_state(|| Some((Piece::Pawn, Color::White)));
+    // a FEN the human is typing, which replaces the position once they load it
+    let fen_input = use_state(|| None::<String>);
+    let error = use_state(|| None::<String>);
+    // this doesn't need a render when it changes, so it isn't state
+    let dragged: Rc<RefCell<Option<DraggedPiece>>> = use_mut_ref(|| None);
+
+    let set_piece = {
+        let position = position.clone();
+        let fen_input = fen_input.clone();
+        let error = error.clone();
+        move |square: Square, piece: Option<(Piece, Color)>, from: Option<Square>| {
+            let mut new_position = (*position).clone();
+            if let Some(from) = from {
+                new_position.pieces[from.to_index()] = None;
+            }
+            new_position.pieces[square.to_index()] = piece;
+            position.set(new_position);
+            fen_input.set(None);
+            error.set(None);
+        }
+    };
+    let prevent_default = Callback::from(|event: DragEvent| event.prevent_default());
+    let start_drag = |piece: DraggedPiece| {
+        let dragged = dragged.clone();
+        Callback::from(move |event: DragEvent| {
+            // firefox won't drag anything without some data
+            if let Some(data_transfer) = event.data_transfer() {
+                let _ = data_transfer.set_data("text/plain", "");
+            }
+            *dragged.borrow_mut() = Some(piece);
+        })
+    };
+
+    let set_side_to_move = edit_position(&position, &fen_input, &error, |position, value| {
+        position.side_to_move = if value == "b" {
+            Color::Black
+        } else {
+            Color::White
+        };
+    });
+    let set_en_passant = edit_position(&position, &fen_input, &error, |position, value| {
+        position.en_passant = value
+            .chars()
+            .next()
+            .filter(|file| ('a'..='h').contains(file))
+            .map(|file| File::from_index(file as usize - 'a' as usize));
+    });
+    let set_halfmove_clock = edit_position(&position, &fen_input, &error, |position, value| {
+        position.halfmove_clock = value.parse::<u32>().unwrap_or(0);
+    });
+    let set_fullmove_number = edit_position(&position, &fen_input, &error, |position, value| {
+        position.fullmove_number = value.parse::<u32>().unwrap_or(1).max(1);
+    });
+    let set_start_position = edit_position(&position, &fen_input, &error, |position, _| {
+        *position = EditorPosition::from_board(&Board::default(), 0, 1);
+    });
+    let clear_board = edit_position(&position, &fen_input, &error, |position, _| {
+        *position = EditorPosition::empty()
+    });
+
+    let load_fen = {
+        let position = position.clone();
+        let fen_input = fen_input.clone();
+        let error = error.clone();
+        Callback::from(move |_| {
+            let Some(fen) = &*fen_input else {
+                return;
+            };
+            match Board::from_str(fen.trim()) {
+                Ok(board) => {
+                    position.set(EditorPosition::from_board(
+                        &board,
+                        halfmove_clock(fen),
+                        fullmove_number(fen),
+                    ));
+                    fen_input.set(None);
+                    error.set(None);
+                }
+                Err(_) => error.set(Some("That FEN isn't a legal position".to_string())),
+            }
+        })
+    };
+    let play = {
+        let position = position.clone();
+        let error = error.clone();
+        let start_position = props.start_position.clone();
+        Callback::from(move |_| match position.validate() {
+            Ok(board) => {
+                start_position.emit((board, position.halfmove_clock, position.fullmove_number))
+            }
+            Err(message) => error.set(Some(message)),
+        })
+    };
+    let cancel = {
+        let cancel = props.cancel.clone();
+        Callback::from(move |_| cancel.emit(()))
+    };
+
+    let palette_piece = |piece: Option<(Piece, Color)>| {
+        let brush_handle = brush.clone();
+        let class = if *brush == piece {
+            "palette-piece selected-brush"
+        } else {
+            "palette-piece"
+        };
+        // dropping a piece from the board onto the eraser takes it off the board
+        let drop_handler = {
+            let dragged = dragged.clone();
+            let set_piece = set_piece.clone();
+            Callback::from(move |event: DragEvent| {
+                event.prevent_default();
+                if let Some((_, _, Some(from))) = dragged.borrow_mut().take() {
+                    set_piece(from, None, None);
+                }
+            })
+        };
+        html! {
+            <button class={class} onclick={Callback::from(move |_| brush_handle.set(piece))}
+                ondragover={prevent_default.clone()} ondrop={drop_handler}>
+                {match piece {
+                    Some((piece, color)) => html! {
+                        <img src={piece_image(piece, color)} alt={piece.to_string(color)} class="piece-image"
+                            draggable="true" ondragstart={start_drag((piece, color, None))} />
+                    },
+                    None => html! { <span class="eraser">{"✕"}</span> },
+                }}
+            </button>
+        }
+    };
+
+    let fen_text = (*fen_input).clone().unwrap_or_else(|| position.to_fen());
+
+    html! {
+        <div class="game-container">
+        <div class="board">
+            { for (0..64).map(|index| {
+                let square = square_at(index, false);
+                let color = if (index / 8 + index % 8) % 2 == 0 { "#e9d9b9" } else { "#aa8a68" };
+                let piece = position.pieces[square.to_index()];
+                let click_handler = {
+                    let set_piece = set_piece.clone();
+                    let brush = *brush;
+                    Callback::from(move |_| {
+                        // painting the piece that is already there takes it away
+                        set_piece(square, if piece == brush { None } else { brush }, None)
+                    })
+                };
+                let drop_handler = {
+                    let dragged = dragged.clone();
+                    let set_piece = set_piece.clone();
+                    Callback::from(move |event: DragEvent| {
+                        event.prevent_default();
+                        if let Some((piece, color, from)) = dragged.borrow_mut().take() {
+                            set_piece(square, Some((piece, color)), from.filter(|from| *from != square));
+                        }
+                    })
+                };
+                html! {
+                    <div class="square" style={format!("background-color: {};", color)}
+                        onclick={click_handler} ondragover={prevent_default.clone()} ondrop={drop_handler}>
+                        if let Some((piece, color)) = piece {
+                            <img src={piece_image(piece, color)} alt={piece.to_string(color)} class="piece-image"
+                                draggable="true" ondragstart={start_drag((piece, color, Some(square)))} />
+                        }
+                    </div>
+                }
+            }) }
+        </div>
+        <div class="side-panel editor-panel">
+            <div class="palette">
+                { for [Color::White, Color::Black].iter().flat_map(|&color| {
+                    PALETTE_PIECES.iter().map(move |&piece| Some((piece, color)))
+                }).map(&palette_piece) }
+                {palette_piece(None)}
+            </div>
+            <label class="editor-option">
+                {"To move"}
+                <select onchange={Callback::from(move |event: Event| {
+                    set_side_to_move(event.target_unchecked_into::<HtmlSelectElement>().value())
+                })}>
+                    <option value="w" selected={position.side_to_move == Color::White}>{"White"}</option>
+                    <option value="b" selected={position.side_to_move == Color::Black}>{"Black"}</option>
+                </select>
+            </label>
+            { for CASTLING_OPTIONS.iter().enumerate().map(|(index, (label, _))| {
+                let set_castling = edit_position(&position, &fen_input, &error, move |position, value| {
+                    position.castling[index] = value == "true";
+                });
+                html! {
+                    <label class="editor-option">
+                        {*label}
+                        <input type="checkbox" checked={position.castling[index]} onchange={Callback::from(move |event: Event| {
+                            set_castling(event.target_unchecked_into::<HtmlInputElement>().checked().to_string())
+                        })}/>
+                    </label>
+                }
+            }) }
+            <label class="editor-option">
+                {"En passant"}
+                <select onchange={Callback::from(move |event: Event| {
+                    set_en_passant(event.target_unchecked_into::<HtmlSelectElement>().value())
+                })}>
+                    <option value="-" selected={position.en_passant.is_none()}>{"-"}</option>
+                    { for ('a'..='h').enumerate().map(|(index, file)| html! {
+                        <option value={file.to_string()}
+                            selected={position.en_passant == Some(File::from_index(index))}>{file}</option>
+                    }) }
+                </select>
+            </label>
+            <label class="editor-option">
+                {"Halfmove clock"}
+                <input type="number" min="0" value={position.halfmove_clock.to_string()}
+                    onchange={Callback::from(move |event: Event| {
+                        set_halfmove_clock(event.target_unchecked_into::<HtmlInputElement>().value())
+                    })}/>
+            </label>
+            <label class="editor-option">
+                {"Move number"}
+                <input type="number" min="1" value={position.fullmove_number.to_string()}
+                    onchange={Callback::from(move |event: Event| {
+                        set_fullmove_number(event.target_unchecked_into::<HtmlInputElement>().value())
+                    })}/>
+            </label>
+            <div class="fen-input">
+                <input type="text" value={fen_text} oninput={{
+                    let fen_input = fen_input.clone();
+                    Callback::from(move |event: InputEvent| {
+                        fen_input.set(Some(event.target_unchecked_into::<HtmlInputElement>().value()))
+                    })
+                }}/>
+                <button class="game-restart-button" onclick={load_fen} disabled={fen_input.is_none()}>{"Load"}</button>
+            </div>
+            if let Some(message) = &*error {
+                <p class="pgn-error">{message}</p>
+            }
+            <div class="button-div">
+                <button class="game-restart-button cancel-button"
+                    onclick={Callback::from(move |_| set_start_position(String::new()))}>{"Reset"}</button>
+                <button class="game-restart-button cancel-button"
+                    onclick={Callback::from(move |_| clear_board(String::new()))}>{"Clear"}</button>
+            </div>
+            <div class="button-div">
+                <button class="game-restart-button" onclick={play}>{"Play"}</button>
+                <button class="game-restart-button cancel-button" onclick={cancel}>{"Cancel"}</button>
+            </div>
+        </div>
+        </div>
+    }
+}
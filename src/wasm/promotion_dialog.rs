@@ -0,0 +1,52 @@
+use chess::{Color, Piece};
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct PromotionDialogProps {
+    pub color: Color,
+    /// emits the piece the pawn should become, or none if the promotion was cancelled
+    pub choose_promotion: Callback<Option<Piece>>,
+}
+
+const PROMOTION_PIECES: [(Piece, &str); 4] = [
+    (Piece::Queen, "Q"),
+    (Piece::Rook, "R"),
+    (Piece::Bishop, "B"),
+    (Piece::Knight, "N"),
+];
+
+#[function_component(PromotionDialog)]
+pub fn promotion_dialog(props: &PromotionDialogProps) -> Html {
+    let color_prefix = if props.color == Color::White {
+        "w"
+    } else {
+        "b"
+    };
+    let choose_promotion = props.choose_promotion.clone();
+    let cancel_click_handler = Callback::from(move |_| choose_promotion.emit(None));
+
+    html! {
+        <div
+        class="game-over"
+        >
+            <div class="restart-game-modal">
+                <p class="game-message">{"Promote to"}</p>
+                <div class="promotion-choices">
+                    { for PROMOTION_PIECES.iter().map(|(piece, piece_letter)| {
+                        let piece = *piece;
+                        let choose_promotion = props.choose_promotion.clone();
+                        let click_handler = Callback::from(move |_| choose_promotion.emit(Some(piece)));
+                        html! {
+                            <button class="promotion-piece" onclick={click_handler}>
+                                <img src={format!("img/{}{}.svg", color_prefix, piece_letter)} alt={*piece_letter} class="piece-image" />
+                            </button>
+                        }
+                    }) }
+                </div>
+                <div class="button-div">
+                    <button class="game-restart-button cancel-button" onclick={cancel_click_handler}>{"Cancel"}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
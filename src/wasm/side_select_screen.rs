@@ -0,0 +1,40 @@
+use chess::Color;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct SideSelectScreenProps {
+    pub choose_side: Callback<Color>,
+}
+
+#[function_component(SideSelectScreen)]
+pub fn side_select_screen(props: &SideSelectScreenProps) -> Html {
+    let choose = |color: Option<Color>| {
+        let choose_side = props.choose_side.clone();
+        Callback::from(move |_| {
+            // no color means the human wants us to flip a coin
+            let color = color.unwrap_or_else(|| {
+                if rand::random::<bool>() {
+                    Color::White
+                } else {
+                    Color::Black
+                }
+            });
+            choose_side.emit(color);
+        })
+    };
+
+    html! {
+        <div
+        class="game-over"
+        >
+            <div class="restart-game-modal">
+                <p class="game-message">{"Play as"}</p>
+                <div class="button-div">
+                    <button class="game-restart-button cancel-button" onclick={choose(Some(Color::White))}>{"White"}</button>
+                    <button class="game-restart-button" onclick={choose(None)}>{"Random"}</button>
+                    <button class="game-restart-button cancel-button" onclick={choose(Some(Color::Black))}>{"Black"}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
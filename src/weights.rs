@@ -0,0 +1,151 @@
+// Generated by the tune binary (src/bin/tune.rs). Rather than editing the numbers by hand, run
+// `cargo run --release --bin tune -- <positions file>` to tune them against a set of positions.
+use crate::eval_weights::EvalWeights;
+
+/// The weights the engine evaluates positions with.
+#[rustfmt::skip]
+pub const WEIGHTS: EvalWeights = EvalWeights {
+    piece_values: [100, 300, 320, 500, 900],
+    no_castle_rights_penalty: 200,
+    midgame_piece_positions: [
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+              5,  10,  10, -20, -20,  10,  10,   5,
+              5,  -5, -10,   0,   0, -10,  -5,   5,
+              0,   0,   0,  20,  20,   0,   0,   0,
+              5,   5,  10,  25,  25,  10,   5,   5,
+             10,  10,  20,  30,  30,  20,  10,  10,
+             50,  50,  50,  50,  50,  50,  50,  50,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+        [
+            -50, -40, -30, -30, -30, -30, -40, -50,
+            -40, -20,   0,   5,   5,   0, -20, -40,
+            -30,   5,  10,  15,  15,  10,   5, -30,
+            -30,   0,  15,  20,  20,  15,   0, -30,
+            -30,   5,  15,  20,  20,  15,   5, -30,
+            -30,   0,  10,  15,  15,  10,   0, -30,
+            -40, -20,   0,   0,   0,   0, -20, -40,
+            -50, -40, -30, -30, -30, -30, -40, -50,
+        ],
+        [
+            -20, -10, -15, -10, -10, -15, -10, -20,
+            -10,   5,   0,   0,   0,   0,   5, -10,
+            -10,  10,  10,  10,  10,  10,  10, -10,
+            -10,   0,  10,  10,  10,  10,   0, -10,
+            -10,   5,   5,  10,  10,   5,   5, -10,
+            -10,   0,   5,  10,  10,   5,   0, -10,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -20, -10, -10, -10, -10, -10, -10, -20,
+        ],
+        [
+              0,   0,   0,   5,   5,   0,   0,   0,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+             -5,   0,   0,   0,   0,   0,   0,  -5,
+              5,  10,  10,  10,  10,  10,  10,   5,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+        [
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+            -10,   0,   5,   0,   0,   0,   0, -10,
+            -10,   5,   5,   5,   5,   5,   0, -10,
+              0,   0,   5,   5,   5,   5,   0,  -5,
+             -5,   0,   5,   5,   5,   5,   0,  -5,
+            -10,   0,   5,   5,   5,   5,   0, -10,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -20, -10, -10,  -5,  -5, -10, -10, -20,
+        ],
+        [
+             20,  30,  10,   0,   0,  10,  30,  20,
+             20,  20,  -5,  -5,  -5,  -5,  20,  20,
+            -10, -20, -20, -20, -20, -20, -20, -10,
+            -20, -30, -30, -40, -40, -30, -30, -20,
+            -30, -40, -40, -50, -50, -40, -40, -30,
+            -40, -50, -50, -60, -60, -50, -50, -40,
+            -60, -60, -60, -60, -60, -60, -60, -60,
+            -80, -70, -70, -70, -70, -70, -70, -80,
+        ],
+    ],
+    endgame_piece_positions: [
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+             10,  10,  10,  10,  10,  10,  10,  10,
+             10,  10,  10,  10,  10,  10,  10,  10,
+             20,  20,  20,  20,  20,  20,  20,  20,
+             30,  30,  30,  30,  30,  30,  30,  30,
+             50,  50,  50,  50,  50,  50,  50,  50,
+             80,  80,  80,  80,  80,  80,  80,  80,
+              0,   0,   0,   0,   0,   0,   0,   0,
+        ],
+        [
+            -50, -40, -30, -30, -30, -30, -40, -50,
+            -40, -20,   0,   0,   0,   0, -20, -40,
+            -30,   0,  10,  15,  15,  10,   0, -30,
+            -30,   5,  15,  20,  20,  15,   5, -30,
+            -30,   5,  15,  20,  20,  15,   5, -30,
+            -30,   0,  10,  15,  15,  10,   0, -30,
+            -40, -20,   0,   0,   0,   0, -20, -40,
+            -50, -40, -30, -30, -30, -30, -40, -50,
+        ],
+        [
+            -20, -10, -10, -10, -10, -10, -10, -20,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -10,   0,   5,  10,  10,   5,   0, -10,
+            -10,   5,  10,  15,  15,  10,   5, -10,
+            -10,   5,  10,  15,  15,  10,   5, -10,
+            -10,   0,   5,  10,  10,   5,   0, -10,
+            -10,   0,   0,   0,   0,   0,   0, -10,
+            -20, -10, -10, -10, -10, -10, -10, -20,
+        ],
+        [
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+              0,   0,   0,   0,   0,   0,   0,   0,
+             10,  10,  10,  10,  10,  10,  10,  10,
+              5,   5,   5,   5,   5,   5,   5,   5,
+        ],
+        [
+            -30, -20, -10, -10, -10, -10, -20, -30,
+            -20, -10,   0,   0,   0,   0, -10, -20,
+            -10,   0,  10,  10,  10,  10,   0, -10,
+            -10,   0,  10,  20,  20,  10,   0, -10,
+            -10,   0,  10,  20,  20,  10,   0, -10,
+            -10,   0,  10,  10,  10,  10,   0, -10,
+            -20, -10,   0,   0,   0,   0, -10, -20,
+            -30, -20, -10, -10, -10, -10, -20, -30,
+        ],
+        [
+            -50, -30, -30, -30, -30, -30, -30, -50,
+            -30, -25,   0,   0,   0,   0, -25, -30,
+            -25, -20,  20,  25,  25,  20, -20, -25,
+            -20, -15,  30,  40,  40,  30, -15, -20,
+            -15, -10,  35,  45,  45,  35, -10, -15,
+            -10,  -5,  20,  30,  30,  20,  -5, -10,
+             -5,   0,   5,   5,   5,   5,   0,  -5,
+            -20, -10, -10, -10, -10, -10, -10, -20,
+        ],
+    ],
+    isolated_pawn_penalty: (10, 20),
+    doubled_pawn_penalty: (10, 25),
+    backward_pawn_penalty: (8, 12),
+    pawn_island_penalty: (5, 10),
+    passed_pawn_bonus: [(0, 0), (5, 10), (10, 15), (15, 25), (30, 45), (50, 80), (80, 130), (0, 0)],
+    supported_pawn_bonus: [(0, 0), (0, 0), (8, 5), (10, 8), (15, 12), (25, 20), (40, 35), (0, 0)],
+    phalanx_pawn_bonus: [(0, 0), (3, 2), (5, 4), (8, 6), (12, 10), (20, 16), (30, 25), (0, 0)],
+    mobility_bonus: [(4, 4), (5, 5), (2, 4), (1, 2)],
+    bishop_pair_bonus: (30, 50),
+    knight_outpost_bonus: (25, 15),
+    rook_open_file_bonus: (35, 15),
+    rook_semi_open_file_bonus: (15, 10),
+    rook_on_seventh_bonus: (20, 35),
+    king_attack_weight: [2, 2, 3, 5],
+    pawn_shield_bonus: [15, 8],
+    king_semi_open_file_penalty: 15,
+    king_open_file_penalty: 15,
+};
@@ -0,0 +1,45 @@
+use std::str::FromStr;
+
+use chess::{Board, ChessMove};
+use rust_chess::epd::Epd;
+
+#[test]
+fn parses_position_and_operations() {
+    let epd = Epd::parse(
+        r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";"#,
+    )
+    .unwrap();
+    assert_eq!(
+        epd.board,
+        Board::from_str("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1").unwrap()
+    );
+    assert_eq!(epd.id(), Some("WAC.001"));
+    assert_eq!(
+        epd.moves("bm").unwrap(),
+        vec![ChessMove::from_str("g3g6").unwrap()]
+    );
+    assert!(epd.moves("am").unwrap().is_empty());
+}
+
+#[test]
+fn keeps_quoted_operands_together() {
+    let epd = Epd::parse(
+        r#"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - am f3 g4; c0 "never; ever" "f3"; id "x""#,
+    )
+    .unwrap();
+    assert_eq!(epd.moves("am").unwrap().len(), 2);
+    assert_eq!(
+        epd.operation("c0").unwrap(),
+        &["never; ever".to_string(), "f3".to_string()]
+    );
+    // the last operation doesn't need a semicolon
+    assert_eq!(epd.id(), Some("x"));
+}
+
+#[test]
+fn rejects_bad_input() {
+    assert!(Epd::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").is_err());
+    assert!(Epd::parse(r#"8/8/8/8/8/8/8/8 w - - id "no kings";"#).is_err());
+    let epd = Epd::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm Qh5;").unwrap();
+    assert!(epd.moves("bm").is_err());
+}
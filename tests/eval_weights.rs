@@ -0,0 +1,24 @@
+use rust_chess::{eval_weights::EvalWeights, weights::WEIGHTS};
+
+#[test]
+fn generated_module_reads_back_the_same_weights() {
+    let mut weights = WEIGHTS.clone();
+    for (index, parameter) in weights.parameters_mut().into_iter().enumerate() {
+        *parameter += index as i32 % 7 - 3;
+    }
+    let source = weights.to_rust_module();
+    assert_eq!(EvalWeights::from_rust_module(&source), Ok(weights));
+}
+
+#[test]
+fn reads_the_checked_in_weights() {
+    let source = include_str!("../src/weights.rs");
+    assert_eq!(EvalWeights::from_rust_module(source), Ok(WEIGHTS));
+}
+
+#[test]
+fn rejects_a_module_with_missing_weights() {
+    let source = WEIGHTS.to_rust_module();
+    let truncated = &source[..source.len() / 2];
+    assert!(EvalWeights::from_rust_module(truncated).is_err());
+}
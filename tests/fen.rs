@@ -0,0 +1,15 @@
+use rust_chess::fen::board_from_fen;
+
+#[test]
+fn fens_without_one_king_of_each_color_are_rejected() {
+    assert!(board_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+    for fen in [
+        "8/8/8/8/8/8/8/8 w - - 0 1",
+        "4k3/8/8/8/8/8/8/8 w - - 0 1",
+        "8/8/8/8/8/8/8/4K3 b - - 0 1",
+        "4k3/8/8/8/8/8/8/2K1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3",
+    ] {
+        assert!(board_from_fen(fen).is_err(), "{}", fen);
+    }
+}
@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use chess::{Board, ChessMove};
+use rust_chess::opening_book::{default_book, OpeningBook, TextBook, WeightedChessMove};
+
+fn weighted(text: &str, weight: u32) -> WeightedChessMove {
+    WeightedChessMove::new(ChessMove::from_str(text).unwrap(), weight)
+}
+
+#[test]
+fn a_text_book_gives_the_moves_of_each_position() {
+    let after_e4 = Board::default().make_move_new(ChessMove::from_str("e2e4").unwrap());
+    let text = format!(
+        "pos {}\ne2e4 30\nd2d4 20\n\npos {}\n  e7e5 5  \n",
+        Board::default().get_hash(),
+        after_e4.get_hash()
+    );
+    let book = TextBook::parse(&text).unwrap();
+    assert_eq!(book.len(), 2);
+    assert_eq!(
+        book.moves(&Board::default()),
+        vec![weighted("e2e4", 30), weighted("d2d4", 20)]
+    );
+    assert_eq!(book.moves(&after_e4), vec![weighted("e7e5", 5)]);
+    assert!(book
+        .moves(&after_e4.make_move_new(ChessMove::from_str("e7e5").unwrap()))
+        .is_empty());
+
+    // the book that ships with the engine has to load, and know the start position
+    let default_book = default_book().unwrap();
+    assert!(!default_book.moves(&Board::default()).is_empty());
+}
+
+#[test]
+fn a_malformed_line_is_reported_with_its_number() {
+    for (text, line, message) in [
+        ("pos 1\ne2e4 30\n\ne2e4\n", 4, "expected two fields"),
+        ("pos 1\ne2e4 30\npos start\n", 3, "invalid position hash"),
+        ("pos 1\ne2e4 30\ne2e9 1\n", 3, "invalid move"),
+        ("pos 1\n\ne2e4 lots\n", 3, "invalid weight"),
+        ("\ne2e4 30\npos 1\n", 2, "before any `pos` line"),
+    ] {
+        let error = TextBook::parse(text).err().unwrap();
+        assert_eq!(error.line, line, "{:?}", text);
+        assert!(error.message.contains(message), "{}", error);
+    }
+}
@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use chess::{Board, MoveGen};
+use rust_chess::perft::{divide, perft};
+
+const STARTING_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+// the known node counts from https://www.chessprogramming.org/Perft_Results, starting at depth 1
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let board = Board::from_str(fen).unwrap();
+    for (depth, expected_nodes) in (1..).zip(expected) {
+        assert_eq!(
+            perft(&board, depth),
+            *expected_nodes,
+            "perft({}) of {}",
+            depth,
+            fen
+        );
+    }
+}
+
+#[test]
+fn starting_position() {
+    assert_perft(STARTING_POSITION, &[20, 400, 8902, 197281, 4865609]);
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039, 97862, 4085603]);
+}
+
+#[test]
+fn position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2812, 43238, 674624]);
+}
+
+#[test]
+fn position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9467, 422333]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft(POSITION_5, &[44, 1486, 62379, 2103487]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft(POSITION_6, &[46, 2079, 89890, 3894594]);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let board = Board::from_str(KIWIPETE).unwrap();
+    let moves = divide(&board, 3);
+    assert_eq!(moves.len(), 48);
+    assert_eq!(moves.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97862);
+}
+
+// making a move has to leave the original board alone, and the incrementally updated hash of the new
+// board has to match the hash of the same position set up from scratch
+fn assert_consistent_hashes(board: &Board, depth: u8) {
+    if depth == 0 {
+        return;
+    }
+    let original = *board;
+    for chess_move in MoveGen::new_legal(board) {
+        let child = board.make_move_new(chess_move);
+        assert_eq!(
+            *board, original,
+            "{} changed after {}",
+            original, chess_move
+        );
+        assert_eq!(board.get_hash(), original.get_hash());
+
+        let mut made_in_place = Board::default();
+        board.make_move(chess_move, &mut made_in_place);
+        assert_eq!(made_in_place, child);
+
+        let from_scratch = Board::from_str(&child.to_string()).unwrap();
+        assert_eq!(
+            child.get_hash(),
+            from_scratch.get_hash(),
+            "hash of {} after {} from {}",
+            child,
+            chess_move,
+            original
+        );
+        assert_consistent_hashes(&child, depth - 1);
+    }
+}
+
+#[test]
+fn make_move_keeps_hashes_consistent() {
+    for fen in [
+        STARTING_POSITION,
+        KIWIPETE,
+        POSITION_3,
+        POSITION_4,
+        POSITION_5,
+        POSITION_6,
+    ] {
+        assert_consistent_hashes(&Board::from_str(fen).unwrap(), 3);
+    }
+}
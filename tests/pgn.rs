@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use chess::{Board, BoardStatus, ChessMove, Game};
+use rust_chess::pgn::{Pgn, PgnError};
+
+// Morphy's opera game, Paris 1858
+const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7
+8. Nc3 c6 9. Bg5 b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7
+14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0
+"#;
+
+fn error(text: &str) -> PgnError {
+    Pgn::parse(text).expect_err("the PGN should have been rejected")
+}
+
+#[test]
+fn a_real_game_survives_a_round_trip() {
+    let pgn = Pgn::parse(OPERA_GAME).unwrap();
+    assert_eq!(pgn.tag("White"), Some("Paul Morphy"));
+    assert_eq!(pgn.tag("Result"), Some("1-0"));
+    assert_eq!(pgn.moves.len(), 33);
+    assert_eq!(
+        pgn.moves[22].chess_move,
+        ChessMove::from_str("e1c1").unwrap()
+    );
+    assert_eq!(
+        pgn.to_game().current_position().status(),
+        BoardStatus::Checkmate
+    );
+
+    let exported = pgn.to_string();
+    assert!(exported.lines().all(|line| line.len() < 80), "{}", exported);
+    assert!(exported.contains("12. O-O-O Rd8"), "{}", exported);
+    assert!(
+        exported.trim_end().ends_with("17. Rd8# 1-0"),
+        "{}",
+        exported
+    );
+    assert_eq!(Pgn::parse(&exported).unwrap(), pgn);
+}
+
+#[test]
+fn comments_and_glyphs_are_kept_but_variations_are_skipped() {
+    let pgn = Pgn::parse(
+        "1. e4 {best by test} e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3!? $5 ; rest of the line\n2... Nc6 *",
+    )
+    .unwrap();
+    let moves: Vec<ChessMove> = pgn
+        .moves
+        .iter()
+        .map(|pgn_move| pgn_move.chess_move)
+        .collect();
+    assert_eq!(
+        moves,
+        ["e2e4", "e7e5", "g1f3", "b8c6"].map(|text| ChessMove::from_str(text).unwrap())
+    );
+    assert_eq!(pgn.moves[0].comment.as_deref(), Some("best by test"));
+    assert_eq!(pgn.moves[1].comment, None);
+    // `!?` and `$5` are the same glyph
+    assert_eq!(pgn.moves[2].nags, vec![5]);
+    assert_eq!(pgn.moves[2].comment.as_deref(), Some("rest of the line"));
+    assert_eq!(pgn.tag("Result"), Some("*"));
+
+    // the seven tag roster is only filled in by `from_game`, so this writes no tags
+    let exported = pgn.to_string();
+    assert!(
+        exported.contains("1. e4 {best by test} 1... e5 2. Nf3 $5 {rest of the line} 2... Nc6 *"),
+        "{}",
+        exported
+    );
+}
+
+#[test]
+fn a_game_from_a_fen_keeps_its_move_counters() {
+    let start_position =
+        Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+            .unwrap();
+    let mut game = Game::new_with_board(start_position);
+    game.make_move(ChessMove::from_str("f1b5").unwrap());
+    game.make_move(ChessMove::from_str("a7a6").unwrap());
+    let pgn = Pgn::from_game(&game, start_position, 2, 3);
+    assert_eq!(pgn.tag("SetUp"), Some("1"));
+    assert_eq!(
+        pgn.tag("FEN"),
+        Some("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+    );
+    assert_eq!(pgn.start_halfmove_clock(), 2);
+    assert_eq!(pgn.first_move_number(), 3);
+
+    let exported = pgn.to_string();
+    assert!(exported.contains("3. Bb5 a6 *"), "{}", exported);
+    let parsed = Pgn::parse(&exported).unwrap();
+    assert_eq!(parsed.start_position, start_position);
+    assert_eq!(parsed.start_halfmove_clock(), 2);
+    assert_eq!(parsed.moves, pgn.moves);
+}
+
+#[test]
+fn malformed_movetext_is_rejected_with_its_line() {
+    let illegal = error("1. e4 e5\n2. Ke3 Nc6 *");
+    assert_eq!(illegal.line, 2);
+    assert!(illegal.message.contains("Ke3"), "{}", illegal);
+
+    assert_eq!(error("1. e4 e5 ) 2. Nf3 *").line, 1);
+    assert_eq!(error("1. e4\n{ never closed\n2. Nf3 *").line, 2);
+    assert_eq!(error("1. e4 e5 $x *").line, 1);
+    assert_eq!(error("").message, "there is no game");
+}
+
+#[test]
+fn bad_tags_are_rejected() {
+    for text in [
+        "[Event \"unterminated]\n\n1. e4 *",
+        "[Event Paris]\n\n1. e4 *",
+        "[Event \"Paris\" extra]\n\n1. e4 *",
+        "[\"Paris\"]\n\n1. e4 *",
+    ] {
+        assert_eq!(error(text).line, 1, "{}", text);
+    }
+
+    let bad_fen = error("[Event \"?\"]\n[FEN \"not a position\"]\n\n1. e4 *");
+    assert!(bad_fen.message.contains("invalid FEN"), "{}", bad_fen);
+}
+
+#[test]
+fn the_fen_tag_gives_the_move_counters() {
+    let pgn = Pgn::parse("[SetUp \"1\"]\n[FEN \"8/8/4k3/8/8/4K3/8/7R w - - 37 61\"]\n\n61. Rh6+ *")
+        .unwrap();
+    assert_eq!(pgn.start_halfmove_clock(), 37);
+    assert_eq!(pgn.first_move_number(), 61);
+    assert_eq!(Pgn::parse("1. e4 *").unwrap().start_halfmove_clock(), 0);
+}
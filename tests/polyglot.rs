@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use chess::{Board, ChessMove};
+use rust_chess::{
+    opening_book::{OpeningBook, WeightedChessMove},
+    polyglot::{decode_move, encode_move, polyglot_key, PolyglotBook},
+};
+
+fn play(moves: &str) -> Board {
+    moves
+        .split_whitespace()
+        .fold(Board::default(), |board, text| {
+            let chess_move = ChessMove::from_str(text).unwrap();
+            assert!(board.legal(chess_move), "{} is illegal", text);
+            board.make_move_new(chess_move)
+        })
+}
+
+#[test]
+fn keys_match_the_reference_positions_of_the_polyglot_spec() {
+    for (moves, key) in [
+        ("", 0x463b96181691fc9c),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+    ] {
+        assert_eq!(polyglot_key(&play(moves)), key, "after `{}`", moves);
+    }
+}
+
+#[test]
+fn castling_is_encoded_as_the_king_taking_its_rook() {
+    let board = Board::from_str("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+    let black_to_move =
+        Board::from_str("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R b KQkq - 0 1").unwrap();
+    for (board, castle, king_takes_rook) in [
+        (&board, "e1g1", "e1h1"),
+        (&board, "e1c1", "e1a1"),
+        (&black_to_move, "e8g8", "e8h8"),
+        (&black_to_move, "e8c8", "e8a8"),
+    ] {
+        let castle = ChessMove::from_str(castle).unwrap();
+        let king_takes_rook = ChessMove::from_str(king_takes_rook).unwrap();
+        let encoded = encode_move(board, castle);
+        assert_eq!(encoded, encode_move(board, king_takes_rook));
+        assert_eq!(decode_move(board, encoded), castle);
+    }
+}
+
+#[test]
+fn other_moves_survive_encoding() {
+    let board = Board::from_str("4k3/1P6/8/8/8/8/6P1/4K2R w K - 0 1").unwrap();
+    for text in ["b7b8q", "b7b8n", "g2g4", "e1f1", "h1h8"] {
+        let chess_move = ChessMove::from_str(text).unwrap();
+        assert_eq!(
+            decode_move(&board, encode_move(&board, chess_move)),
+            chess_move,
+            "{}",
+            text
+        );
+    }
+    // e2e4 in the spec's own layout: to file, to rank, from file, from rank, promotion
+    let e2e4 = ChessMove::from_str("e2e4").unwrap();
+    assert_eq!(
+        encode_move(&Board::default(), e2e4),
+        4 | (3 << 3) | (4 << 6) | (1 << 9)
+    );
+}
+
+#[test]
+fn book_moves_are_found_by_key() {
+    let entry = |key: u64, encoded_move: u16, weight: u16| {
+        let mut bytes = Vec::new();
+        bytes.extend(key.to_be_bytes());
+        bytes.extend(encoded_move.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes
+    };
+    let after_e4 = play("e2e4");
+    let castling = Board::from_str("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+    // out of order on purpose, since not every book is sorted
+    let bytes = [
+        entry(polyglot_key(&castling), 0o0407, 3),
+        entry(0x463b96181691fc9c, 0o1434, 10),
+        entry(polyglot_key(&after_e4), 0o6444, 7),
+        entry(0x463b96181691fc9c, 0o1333, 5),
+    ]
+    .concat();
+    let book = PolyglotBook::from_bytes(&bytes).unwrap();
+    assert_eq!(book.len(), 4);
+
+    let weighted = |text: &str, weight: u32| {
+        WeightedChessMove::new(ChessMove::from_str(text).unwrap(), weight)
+    };
+    assert_eq!(
+        book.moves(&Board::default()),
+        vec![weighted("e2e4", 10), weighted("d2d4", 5)]
+    );
+    assert_eq!(book.moves(&after_e4), vec![weighted("e7e5", 7)]);
+    assert_eq!(book.moves(&castling), vec![weighted("e1g1", 3)]);
+    assert!(book.moves(&play("d2d4")).is_empty());
+
+    let mut written = Vec::new();
+    book.write(&mut written).unwrap();
+    assert_eq!(
+        PolyglotBook::from_bytes(&written).unwrap().moves(&after_e4),
+        book.moves(&after_e4)
+    );
+    assert!(PolyglotBook::from_bytes(&bytes[..20]).is_err());
+}
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use chess::{Action, Board, ChessMove, Color};
+use rust_chess::san::{game_to_san, move_to_san, san_to_move};
+
+fn san(fen: &str, chess_move: &str) -> String {
+    move_to_san(
+        &Board::from_str(fen).unwrap(),
+        ChessMove::from_str(chess_move).unwrap(),
+    )
+}
+
+#[test]
+fn pieces_are_only_told_apart_when_they_have_to_be() {
+    let knights = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+    assert_eq!(san(knights, "b1d2"), "Nbd2");
+    assert_eq!(san(knights, "f3d2"), "Nfd2");
+    assert_eq!(san(knights, "b1c3"), "Nc3");
+
+    let rooks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+    assert_eq!(san(rooks, "a1a3"), "R1a3");
+    assert_eq!(san(rooks, "a5a3"), "R5a3");
+
+    // a1 shares its rank with c1 and its file with a3, so only the whole square will do
+    let queens = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+    assert_eq!(san(queens, "a1b2"), "Qa1b2");
+    assert_eq!(san(queens, "c1b2"), "Qcb2");
+    assert_eq!(san(queens, "a3b2"), "Q3b2");
+}
+
+#[test]
+fn captures_promotions_and_castling() {
+    assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+    assert_eq!(san("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q"), "bxa8=Q+");
+    assert_eq!(san("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n"), "b8=N");
+
+    let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(san(castling, "e1g1"), "O-O");
+    assert_eq!(san(castling, "e1c1"), "O-O-O");
+    assert_eq!(san(castling, "a1a8"), "Rxa8+");
+}
+
+#[test]
+fn checkmate_is_marked_with_a_hash() {
+    let fools_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2";
+    assert_eq!(san(fools_mate, "d8h4"), "Qh4#");
+}
+
+#[test]
+fn san_is_read_back_leniently() {
+    let board = Board::from_str("r3k2r/1P6/8/8/8/1N3N2/8/R3K2R w KQkq - 0 1").unwrap();
+    let read = |text: &str| san_to_move(&board, text).map(|chess_move| chess_move.to_string());
+    assert_eq!(read("Nbd2").as_deref(), Some("b3d2"));
+    assert_eq!(read("Nfd2").as_deref(), Some("f3d2"));
+    assert_eq!(read("O-O").as_deref(), Some("e1g1"));
+    assert_eq!(read("0-0-0").as_deref(), Some("e1c1"));
+    assert_eq!(read("bxa8=Q+").as_deref(), Some("b7a8q"));
+    assert_eq!(read("bxa8Q").as_deref(), Some("b7a8q"));
+    assert_eq!(read("b8=N!?").as_deref(), Some("b7b8n"));
+
+    // too vague, illegal, or not a move at all
+    assert_eq!(read("Nd2"), None);
+    assert_eq!(read("Ke3"), None);
+    assert_eq!(read(""), None);
+}
+
+#[test]
+fn games_skip_everything_that_isnt_a_move() {
+    let actions = [
+        Action::MakeMove(ChessMove::from_str("e2e4").unwrap()),
+        Action::OfferDraw(Color::Black),
+        Action::MakeMove(ChessMove::from_str("e7e5").unwrap()),
+        Action::Resign(Color::White),
+    ];
+    assert_eq!(game_to_san(&Board::default(), &actions), ["e4", "e5"]);
+}
@@ -0,0 +1,442 @@
+use std::{str::FromStr, sync::atomic::AtomicBool};
+
+use chess::{Board, BoardStatus, ChessMove, MoveGen};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use rust_chess::{
+    constants::DEFAULT_CONTEMPT,
+    game_history::GameHistory,
+    search::{iterative_deepening, Score, SearchOptions, SearchResult},
+    search_limits::{SearchLimits, SystemClock},
+    transposition_table::TranspositionTable,
+};
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn search(board: &Board, history: &GameHistory, depth: u8) -> SearchResult {
+    search_with(
+        board,
+        history,
+        &SearchLimits::depth(depth),
+        &SearchOptions::default(),
+        &mut TranspositionTable::new(1),
+    )
+}
+
+fn search_with(
+    board: &Board,
+    history: &GameHistory,
+    limits: &SearchLimits,
+    options: &SearchOptions,
+    transposition_table: &mut TranspositionTable,
+) -> SearchResult {
+    let stop = AtomicBool::new(false);
+    iterative_deepening(
+        board,
+        history,
+        limits,
+        options,
+        transposition_table,
+        &SystemClock,
+        &stop,
+        |_| {},
+    )
+}
+
+fn moves(board: &Board, moves: &str) -> Vec<ChessMove> {
+    let mut position = *board;
+    moves
+        .split_whitespace()
+        .map(|text| {
+            let chess_move = ChessMove::from_str(text).unwrap();
+            assert!(position.legal(chess_move), "{} is illegal", text);
+            position = position.make_move_new(chess_move);
+            chess_move
+        })
+        .collect()
+}
+
+fn play(board: &Board, moves: &[ChessMove]) -> Board {
+    moves.iter().fold(*board, |position, chess_move| {
+        position.make_move_new(*chess_move)
+    })
+}
+
+#[test]
+fn history_counts_repetitions_since_the_last_irreversible_move() {
+    let start = Board::default();
+    let shuffle = moves(&start, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+    let history = GameHistory::from_moves(&start, 0, &shuffle);
+    assert_eq!(history.repetitions(&play(&start, &shuffle)), 2);
+    assert_eq!(history.halfmove_clock(), 8);
+
+    let pawn_move = moves(&start, "g1f3 g8f6 f3g1 f6g8 e2e4");
+    let history = GameHistory::from_moves(&start, 0, &pawn_move);
+    assert!(history.hashes().is_empty());
+    assert_eq!(history.halfmove_clock(), 0);
+}
+
+#[test]
+fn losing_side_repeats_the_position_a_third_time() {
+    // black is a queen down, but going back to b8 repeats the starting position for the third time
+    let start = Board::from_str("1n2k3/8/8/8/8/8/Q7/4K1N1 w - - 0 1").unwrap();
+    let shuffle = moves(&start, "g1f3 b8c6 f3g1 c6b8 g1f3 b8c6 f3g1");
+    let board = play(&start, &shuffle);
+
+    let result = search(&board, &GameHistory::from_moves(&start, 0, &shuffle), 3);
+    assert_eq!(result.best_move, Some(ChessMove::from_str("c6b8").unwrap()));
+    assert_eq!(result.score, Score::Centipawns(-DEFAULT_CONTEMPT));
+
+    // without the history it's just a lost position
+    let result = search(&board, &GameHistory::default(), 3);
+    assert!(matches!(result.score, Score::Centipawns(score) if score < -500));
+}
+
+#[test]
+fn a_reused_table_still_sees_the_draws_of_this_game() {
+    // the same position as above, first searched as if it had no history, which fills the table with scores
+    // that know nothing of the repetition
+    let start = Board::from_str("1n2k3/8/8/8/8/8/Q7/4K1N1 w - - 0 1").unwrap();
+    let shuffle = moves(&start, "g1f3 b8c6 f3g1 c6b8 g1f3 b8c6 f3g1");
+    let board = play(&start, &shuffle);
+    let mut transposition_table = TranspositionTable::new(1);
+    let options = SearchOptions::default();
+    let limits = SearchLimits::depth(3);
+    search_with(
+        &board,
+        &GameHistory::default(),
+        &limits,
+        &options,
+        &mut transposition_table,
+    );
+
+    let result = search_with(
+        &board,
+        &GameHistory::from_moves(&start, 0, &shuffle),
+        &limits,
+        &options,
+        &mut transposition_table,
+    );
+    assert_eq!(result.best_move, Some(ChessMove::from_str("c6b8").unwrap()));
+    assert_eq!(result.score, Score::Centipawns(-DEFAULT_CONTEMPT));
+}
+
+#[test]
+fn fifty_move_rule_draws_a_won_position() {
+    // white can't mate, capture or move a pawn, so every move ends the game on the hundredth ply
+    let board = Board::from_str("8/8/8/7k/8/8/1Q6/K7 w - - 0 1").unwrap();
+    let result = search(&board, &GameHistory::new(99), 3);
+    assert_eq!(result.score, Score::Centipawns(-DEFAULT_CONTEMPT));
+
+    let result = search(&board, &GameHistory::new(0), 3);
+    assert!(matches!(result.score, Score::Centipawns(score) if score > 500));
+}
+
+#[test]
+fn a_deep_search_of_a_sparse_ending_finishes() {
+    // with so few pieces the search gets past 16 plies quickly, where the squared depth of a history
+    // cutoff no longer fits in a byte
+    let board = Board::from_str("8/8/8/4k3/8/8/8/4K2R w K - 0 1").unwrap();
+    let result = search(&board, &GameHistory::default(), 17);
+    assert_eq!(result.depth, 17);
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn a_search_stopped_right_away_still_finishes_depth_1() {
+    let board = Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 0 3")
+        .unwrap();
+    let expected = search(&board, &GameHistory::default(), 1);
+    let stop = AtomicBool::new(true);
+    for limits in [
+        SearchLimits::infinite(),
+        SearchLimits::move_time(0),
+        SearchLimits::infinite().with_max_nodes(1),
+    ] {
+        let result = iterative_deepening(
+            &board,
+            &GameHistory::default(),
+            &limits,
+            &SearchOptions::default(),
+            &mut TranspositionTable::new(1),
+            &SystemClock,
+            &stop,
+            |_| {},
+        );
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.best_move, expected.best_move);
+    }
+}
+
+// a position from a random game, along with the game that led to it
+fn random_position(rng: &mut StdRng) -> (Board, GameHistory) {
+    let start = Board::default();
+    let mut board = start;
+    let mut played = Vec::new();
+    for _ in 0..rng.gen_range(4..40) {
+        let Some(chess_move) = MoveGen::new_legal(&board).choose(rng) else {
+            break;
+        };
+        let next = board.make_move_new(chess_move);
+        if next.status() != BoardStatus::Ongoing {
+            break;
+        }
+        played.push(chess_move);
+        board = next;
+    }
+    (board, GameHistory::from_moves(&start, 0, &played))
+}
+
+#[test]
+fn transposition_table_never_changes_the_score() {
+    // extensions, reductions and pruning all depend on how a position was reached or on the window, and
+    // contempt on whose move it was at the root. with them off, the score of a position at a given depth
+    // is the same however the search got there, so the table can save work but never change the score
+    let plain = SearchOptions {
+        contempt: 0,
+        ..plain_options()
+    };
+    let with_table = SearchOptions {
+        transposition_table: true,
+        ..plain.clone()
+    };
+    // without delta pruning, random games full of hanging pieces can have huge capture sequences, so the
+    // positions where plain alpha-beta doesn't finish in time are left out
+    let plain_search = |board: &Board, history: &GameHistory, depth: u8| {
+        let limits = SearchLimits::depth(depth).with_max_nodes(20_000);
+        let result = search_with(
+            board,
+            history,
+            &limits,
+            &plain,
+            &mut TranspositionTable::new(0),
+        );
+        (result.depth == depth).then_some(result.score)
+    };
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let mut compared = 0;
+    for _ in 0..12 {
+        let (board, history) = random_position(&mut rng);
+        let Some(expected) = plain_search(&board, &history, 3) else {
+            continue;
+        };
+        // the one-bucket table is full of collisions
+        for size_mb in [0, 1] {
+            let mut transposition_table = TranspositionTable::new(size_mb);
+            let limits = SearchLimits::depth(3);
+            let result = search_with(
+                &board,
+                &history,
+                &limits,
+                &with_table,
+                &mut transposition_table,
+            );
+            assert_eq!(
+                result.score, expected,
+                "{} with a {} MB table",
+                board, size_mb
+            );
+            compared += 1;
+
+            // the table is now full of bounds from windows the children were searched with, which have to
+            // hold up under the full window
+            for chess_move in MoveGen::new_legal(&board) {
+                let child = board.make_move_new(chess_move);
+                let mut child_history = history.clone();
+                child_history.push(&board, chess_move);
+                let Some(expected) = plain_search(&child, &child_history, 2) else {
+                    continue;
+                };
+                let result = search_with(
+                    &child,
+                    &child_history,
+                    &SearchLimits::depth(2),
+                    &with_table,
+                    &mut transposition_table,
+                );
+                assert_eq!(
+                    result.score, expected,
+                    "{} after {} with a {} MB table",
+                    board, chess_move, size_mb
+                );
+                compared += 1;
+            }
+        }
+    }
+    assert!(compared >= 100, "only {} searches were compared", compared);
+}
+
+// every part of the search that can be switched off, switched off
+fn plain_options() -> SearchOptions {
+    SearchOptions {
+        transposition_table: false,
+        check_extensions: false,
+        late_move_reductions: false,
+        delta_pruning: false,
+        null_move_pruning: false,
+        reverse_futility_pruning: false,
+        futility_pruning: false,
+        late_move_pruning: false,
+        principal_variation_search: false,
+        aspiration_windows: false,
+        ..SearchOptions::default()
+    }
+}
+
+#[test]
+fn pruning_still_finds_a_quiet_mate() {
+    // 1. Kb6 Kb8 2. Rh8# or 1. Kc7 Ka7 2. Ra1#, where the first move is as quiet as they come
+    let board = Board::from_str("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+    let only = |enable: fn(&mut SearchOptions)| {
+        let mut options = plain_options();
+        enable(&mut options);
+        options
+    };
+    let configurations = [
+        ("nothing", plain_options()),
+        (
+            "late move reductions",
+            only(|options| options.late_move_reductions = true),
+        ),
+        (
+            "null move pruning",
+            only(|options| options.null_move_pruning = true),
+        ),
+        (
+            "reverse futility pruning",
+            only(|options| options.reverse_futility_pruning = true),
+        ),
+        (
+            "futility pruning",
+            only(|options| options.futility_pruning = true),
+        ),
+        (
+            "late move pruning",
+            only(|options| options.late_move_pruning = true),
+        ),
+        ("everything", SearchOptions::default()),
+    ];
+    for (name, options) in configurations {
+        let result = search_with(
+            &board,
+            &GameHistory::default(),
+            &SearchLimits::depth(5),
+            &options,
+            &mut TranspositionTable::new(1),
+        );
+        assert_eq!(result.score, Score::Mate(2), "with {}", name);
+    }
+}
+
+#[test]
+fn null_move_pruning_stays_out_of_pawn_endings() {
+    // zugzwang decides pawn endings, so passing would be a terrible guess at how good the position is
+    let board = Board::from_str("8/5p2/4k3/4P3/3K4/8/5P2/8 w - - 0 1").unwrap();
+    let without = plain_options();
+    let with = SearchOptions {
+        null_move_pruning: true,
+        ..without.clone()
+    };
+    let search = |options: &SearchOptions| {
+        search_with(
+            &board,
+            &GameHistory::default(),
+            &SearchLimits::depth(6),
+            options,
+            &mut TranspositionTable::new(1),
+        )
+    };
+    let (with, without) = (search(&with), search(&without));
+    assert_eq!(with.score, without.score);
+    assert_eq!(with.nodes, without.nodes);
+}
+
+#[test]
+fn pruning_and_reductions_cut_down_the_tree() {
+    let board =
+        Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+            .unwrap();
+    let search = |options: &SearchOptions| {
+        search_with(
+            &board,
+            &GameHistory::default(),
+            &SearchLimits::depth(5),
+            options,
+            &mut TranspositionTable::new(1),
+        )
+    };
+    let everything = SearchOptions::default();
+    let without_selectivity = SearchOptions {
+        late_move_reductions: false,
+        null_move_pruning: false,
+        reverse_futility_pruning: false,
+        futility_pruning: false,
+        late_move_pruning: false,
+        ..SearchOptions::default()
+    };
+    let (selective, full_width) = (search(&everything), search(&without_selectivity));
+    assert!(
+        selective.nodes * 2 < full_width.nodes,
+        "{} nodes with selectivity, {} without",
+        selective.nodes,
+        full_width.nodes
+    );
+}
+
+#[test]
+fn principal_variation_search_shrinks_the_tree() {
+    let search = |fen: &str, depth: u8, options: &SearchOptions| {
+        search_with(
+            &Board::from_str(fen).unwrap(),
+            &GameHistory::default(),
+            &SearchLimits::depth(depth),
+            options,
+            &mut TranspositionTable::new(16),
+        )
+    };
+    let with_pvs = |options: &SearchOptions| SearchOptions {
+        principal_variation_search: true,
+        aspiration_windows: true,
+        ..options.clone()
+    };
+
+    // without the table or any pruning the score can't depend on the window, so both searches have to agree
+    // on it exactly
+    let plain = plain_options();
+    for (fen, depth) in [
+        (START_FEN, 5),
+        ("8/5pk1/6p1/3R4/6P1/5K2/r7/8 w - - 0 40", 6),
+        ("4k3/2p5/3p4/1P1P4/8/8/5K2/8 w - - 0 1", 8),
+    ] {
+        assert_eq!(
+            search(fen, depth, &with_pvs(&plain)).score,
+            search(fen, depth, &plain).score,
+            "{}",
+            fen
+        );
+    }
+
+    // the re-searches are cheap once the table knows the position, and most of the pruning only kicks in
+    // when the window is nothing but a bound to beat, so it's with everything else that the tree shrinks
+    let without_pvs = SearchOptions {
+        principal_variation_search: false,
+        aspiration_windows: false,
+        ..SearchOptions::default()
+    };
+    let (mut nodes_with, mut nodes_without) = (0, 0);
+    for (fen, depth) in [
+        (START_FEN, 7),
+        (
+            "r2q1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R2Q1RK1 w - - 0 10",
+            5,
+        ),
+    ] {
+        nodes_with += search(fen, depth, &with_pvs(&without_pvs)).nodes;
+        nodes_without += search(fen, depth, &without_pvs).nodes;
+    }
+    assert!(
+        nodes_with * 2 < nodes_without,
+        "{} nodes with principal variation search, {} without",
+        nodes_with,
+        nodes_without
+    );
+}
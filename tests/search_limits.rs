@@ -0,0 +1,24 @@
+use rust_chess::search_limits::SearchLimits;
+
+#[test]
+fn a_big_increment_never_spends_more_than_a_quarter_of_the_clock() {
+    for (time_left, increment) in [(1000, 2000), (200, 5000), (60, 1000), (10_000, 100_000)] {
+        let limits = SearchLimits::from_clock(time_left, increment, None);
+        let (soft, hard) = (limits.soft_time_ms.unwrap(), limits.hard_time_ms.unwrap());
+        assert!(
+            hard <= (time_left / 4).max(1),
+            "{} ms left with a {} ms increment gave a hard limit of {} ms",
+            time_left,
+            increment,
+            hard
+        );
+        assert!(soft <= hard);
+    }
+}
+
+#[test]
+fn a_long_clock_is_shared_between_the_moves_to_go() {
+    let limits = SearchLimits::from_clock(60_000, 0, Some(20));
+    assert_eq!(limits.soft_time_ms, Some(3000));
+    assert_eq!(limits.hard_time_ms, Some(9000));
+}
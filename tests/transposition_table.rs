@@ -0,0 +1,91 @@
+use chess::{ChessMove, Square};
+use rust_chess::transposition_table::{TranspositionTable, Type};
+
+// a table this small has a single bucket, so every position competes for the same two slots
+fn tiny_table() -> TranspositionTable {
+    TranspositionTable::new(0)
+}
+
+fn stored_eval(table: &TranspositionTable, hash: u64) -> Option<i32> {
+    table.probe(hash, 0, 0, -1000, 1000).cutoff
+}
+
+#[test]
+fn colliding_positions_are_told_apart() {
+    let mut table = tiny_table();
+    table.add(1, 10, 5, Type::Exact, None, 0);
+    table.add(2, 20, 3, Type::Exact, None, 0);
+    assert_eq!(stored_eval(&table, 1), Some(10));
+    assert_eq!(stored_eval(&table, 2), Some(20));
+    assert_eq!(stored_eval(&table, 3), None);
+}
+
+#[test]
+fn deeper_entries_survive_shallow_ones() {
+    let mut table = tiny_table();
+    table.add(1, 10, 5, Type::Exact, None, 0);
+    table.add(2, 20, 3, Type::Exact, None, 0);
+    table.add(3, 30, 1, Type::Exact, None, 0);
+    assert_eq!(stored_eval(&table, 1), Some(10));
+    assert_eq!(stored_eval(&table, 2), None);
+    assert_eq!(stored_eval(&table, 3), Some(30));
+}
+
+#[test]
+fn entries_from_earlier_searches_get_replaced() {
+    let mut table = tiny_table();
+    table.add(1, 10, 5, Type::Exact, None, 0);
+    table.new_search();
+    table.add(2, 20, 1, Type::Exact, None, 0);
+    assert_eq!(stored_eval(&table, 1), None);
+    assert_eq!(stored_eval(&table, 2), Some(20));
+}
+
+#[test]
+fn hashfull_counts_entries_from_the_current_search() {
+    let mut table = tiny_table();
+    assert_eq!(table.hashfull(), 0);
+    table.add(1, 10, 5, Type::Exact, None, 0);
+    assert_eq!(table.hashfull(), 500);
+    table.add(2, 20, 3, Type::Exact, None, 0);
+    assert_eq!(table.hashfull(), 1000);
+    table.new_search();
+    assert_eq!(table.hashfull(), 0);
+    table.clear();
+    assert_eq!(stored_eval(&table, 1), None);
+}
+
+#[test]
+fn bounds_only_settle_the_side_of_the_window_they_describe() {
+    let mut table = tiny_table();
+    // failed high with 50: the true score is at least 50
+    table.add(1, 50, 4, Type::LowerBound, None, 0);
+    assert_eq!(table.probe(1, 4, 0, 0, 40).cutoff, Some(50));
+    assert_eq!(table.probe(1, 4, 0, 0, 60).cutoff, None);
+    // failed low with -50: the true score is at most -50
+    table.add(2, -50, 4, Type::UpperBound, None, 0);
+    assert_eq!(table.probe(2, 4, 0, -40, 0).cutoff, Some(-50));
+    assert_eq!(table.probe(2, 4, 0, -60, 0).cutoff, None);
+
+    assert_eq!(Type::for_window(60, 0, 50), Type::LowerBound);
+    assert_eq!(Type::for_window(0, 0, 50), Type::UpperBound);
+    assert_eq!(Type::for_window(25, 0, 50), Type::Exact);
+}
+
+#[test]
+fn unusable_entries_still_give_their_move() {
+    let mut table = tiny_table();
+    let chess_move = ChessMove::new(Square::E2, Square::E4, None);
+    table.add(1, 30, 2, Type::LowerBound, Some(chess_move), 0);
+    // too shallow
+    let probe = table.probe(1, 3, 0, -1000, 1000);
+    assert_eq!(probe.cutoff, None);
+    assert_eq!(probe.best_move, Some(chess_move));
+    // deep enough, but the bound is on the wrong side of the window
+    let probe = table.probe(1, 2, 0, 0, 100);
+    assert_eq!(probe.cutoff, None);
+    assert_eq!(probe.best_move, Some(chess_move));
+    // a later fail low keeps the move it doesn't know better than
+    table.add(1, -10, 3, Type::UpperBound, None, 0);
+    assert_eq!(table.probe(1, 3, 0, 0, 100).best_move, Some(chess_move));
+}